@@ -0,0 +1,80 @@
+#[cfg(feature = "hashbrown")]
+use alloc::string::String;
+
+use rkyv::{
+    api::serialize_using,
+    rancor::{Source, Strategy},
+    ser::{allocator::ArenaHandle, sharing::Share, Serializer},
+    util::{with_arena, AlignedVec},
+    Serialize,
+};
+
+#[cfg(feature = "hashbrown")]
+use crate::Interner;
+use crate::InterningAdapter;
+
+/// The serializer used by [`to_bytes`] and [`to_bytes_with`].
+///
+/// This combines a writer `W`, an arena allocator, pointer sharing, and the
+/// interning `I`. It can be used to name the serializer type in bounds and
+/// fields.
+pub type InterningSerializer<'a, W, I, E> =
+    Strategy<InterningAdapter<Serializer<W, ArenaHandle<'a>, Share>, I>, E>;
+
+/// Serializes the given value with interning and returns the resulting bytes.
+///
+/// This is the interning counterpart to `rkyv::to_bytes`. Values are interned
+/// with a default [`Interner<String>`](Interner), so fields interned as `str`
+/// share archived copies. Use [`to_bytes_with`] to intern other types or to
+/// configure the interning.
+///
+/// # Example
+///
+/// ```
+/// use rkyv::{rancor::Error, Archive, Serialize};
+/// use rkyv_intern::BorrowIntern;
+///
+/// #[derive(Archive, Serialize)]
+/// struct Log {
+///     #[rkyv(with = BorrowIntern<str>)]
+///     user: String,
+/// }
+///
+/// let logs = ["alice", "bob", "alice"]
+///     .iter()
+///     .map(|user| Log {
+///         user: user.to_string(),
+///     })
+///     .collect::<Vec<_>>();
+/// let bytes = rkyv_intern::to_bytes::<Error>(&logs).unwrap();
+/// ```
+#[cfg(feature = "hashbrown")]
+pub fn to_bytes<E>(
+    value: &impl for<'a> Serialize<
+        InterningSerializer<'a, AlignedVec, Interner<String>, E>,
+    >,
+) -> Result<AlignedVec, E>
+where
+    E: Source,
+{
+    to_bytes_with(value, Interner::default())
+}
+
+/// Serializes the given value with the given interning and returns the
+/// resulting bytes.
+pub fn to_bytes_with<I, E>(
+    value: &impl for<'a> Serialize<InterningSerializer<'a, AlignedVec, I, E>>,
+    interning: I,
+) -> Result<AlignedVec, E>
+where
+    E: Source,
+{
+    with_arena(|arena| {
+        let mut serializer = InterningAdapter::new(
+            Serializer::new(AlignedVec::new(), arena.acquire(), Share::new()),
+            interning,
+        );
+        serialize_using::<_, E>(value, &mut serializer)?;
+        Ok(serializer.into_serializer().into_writer())
+    })
+}
@@ -0,0 +1,189 @@
+use core::{error::Error, fmt};
+
+use rkyv::{
+    rancor::{fail, Source},
+    ser::sharing::SharingState,
+};
+
+use crate::{AlreadyFinished, Interning, NotStarted};
+
+/// A fixed-capacity value interner that does not allocate.
+///
+/// Values are stored inline and searched linearly, so this is best suited to
+/// small sets of distinct values. Once all `N` slots are in use, finishing a
+/// new value returns an error.
+///
+/// With the `heapless` feature, an `ArrayInterner<heapless::String<L>, N>` can
+/// intern `str` values, so `heapless::String` fields can be serialized with
+/// [`DerefIntern`](crate::DerefIntern) without `alloc`.
+pub struct ArrayInterner<T, const N: usize> {
+    entries: [Option<(T, Option<usize>)>; N],
+    len: usize,
+}
+
+impl<T, const N: usize> ArrayInterner<T, N> {
+    /// Returns a new, empty interner.
+    pub fn new() -> Self {
+        Self {
+            entries: core::array::from_fn(|_| None),
+            len: 0,
+        }
+    }
+
+    /// Removes all values from the interner.
+    pub fn clear(&mut self) {
+        self.entries[..self.len].fill_with(|| None);
+        self.len = 0;
+    }
+
+    /// Returns the number of values in the interner.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the interner is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn find_mut(
+        &mut self,
+        is_match: impl Fn(&T) -> bool,
+    ) -> Option<&mut Option<usize>> {
+        self.entries[..self.len]
+            .iter_mut()
+            .flatten()
+            .find(|(v, _)| is_match(v))
+            .map(|(_, pos)| pos)
+    }
+
+    fn start(
+        &mut self,
+        is_match: impl Fn(&T) -> bool,
+        to_owned: impl FnOnce() -> Option<T>,
+    ) -> SharingState {
+        match self.find_mut(is_match) {
+            Some(None) => SharingState::Pending,
+            Some(Some(pos)) => SharingState::Finished(*pos),
+            None => {
+                if self.len < N {
+                    if let Some(value) = to_owned() {
+                        self.entries[self.len] = Some((value, None));
+                        self.len += 1;
+                    }
+                }
+                SharingState::Started
+            }
+        }
+    }
+
+    fn finish<E: Source>(
+        &mut self,
+        is_match: impl Fn(&T) -> bool,
+        pos: usize,
+    ) -> Result<(), E> {
+        let is_full = self.len == N;
+        match self.find_mut(is_match) {
+            None if is_full => fail!(InternerFull { capacity: N }),
+            None => fail!(NotStarted),
+            Some(Some(existing)) => fail!(AlreadyFinished {
+                value: None,
+                existing: *existing,
+                new: pos,
+            }),
+            Some(x) => {
+                *x = Some(pos);
+                Ok(())
+            }
+        }
+    }
+
+    fn contains_by(&self, is_match: impl Fn(&T) -> bool) -> bool {
+        self.entries[..self.len]
+            .iter()
+            .flatten()
+            .any(|(v, _)| is_match(v))
+    }
+}
+
+impl<T, const N: usize> Default for ArrayInterner<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug)]
+struct InternerFull {
+    capacity: usize,
+}
+
+impl fmt::Display for InternerFull {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "interner is full: all {} slots are already in use",
+            self.capacity
+        )
+    }
+}
+
+impl Error for InternerFull {}
+
+impl<T, E, const N: usize> Interning<T, E> for ArrayInterner<T, N>
+where
+    T: Clone + Eq,
+    E: Source,
+{
+    fn start_interning(&mut self, value: &T) -> SharingState {
+        self.start(|v| v == value, || Some(value.clone()))
+    }
+
+    fn finish_interning(&mut self, value: &T, pos: usize) -> Result<(), E> {
+        self.finish(|v| v == value, pos)
+    }
+
+    fn contains(&self, value: &T) -> bool {
+        self.contains_by(|v| v == value)
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+/// Interns `str` values as `heapless::String`s.
+///
+/// Strings longer than `L` bytes don't fit in a slot, so they are serialized
+/// without interning.
+#[cfg(feature = "heapless")]
+impl<E, const L: usize, const N: usize> Interning<str, E>
+    for ArrayInterner<heapless::String<L>, N>
+where
+    E: Source,
+{
+    fn start_interning(&mut self, value: &str) -> SharingState {
+        self.start(
+            |v| v == value,
+            || {
+                let mut owned = heapless::String::new();
+                owned.push_str(value).ok().map(|()| owned)
+            },
+        )
+    }
+
+    fn finish_interning(&mut self, value: &str, pos: usize) -> Result<(), E> {
+        if value.len() > L {
+            Ok(())
+        } else {
+            self.finish(|v| v == value, pos)
+        }
+    }
+
+    fn contains(&self, value: &str) -> bool {
+        self.contains_by(|v| v == value)
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
@@ -0,0 +1,106 @@
+use alloc::{borrow::ToOwned, collections::BTreeMap};
+use core::{borrow::Borrow, mem::size_of};
+
+use rkyv::{
+    rancor::{fail, Source},
+    ser::sharing::SharingState,
+};
+
+use crate::{AlreadyFinished, Interning, NotStarted};
+
+/// A value interner backed by a `BTreeMap`.
+///
+/// Unlike the hash-based interners, this does not hash values and so behaves
+/// identically across runs and platforms. It is available without the
+/// `hashbrown` feature.
+pub struct BTreeInterner<T> {
+    value_to_pos: BTreeMap<T, Option<usize>>,
+}
+
+impl<T> BTreeInterner<T> {
+    /// Returns a new, empty interner.
+    pub fn new() -> Self {
+        Self {
+            value_to_pos: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the number of values in the interner.
+    pub fn len(&self) -> usize {
+        self.value_to_pos.len()
+    }
+
+    /// Returns whether the interner is empty.
+    pub fn is_empty(&self) -> bool {
+        self.value_to_pos.is_empty()
+    }
+
+    /// Returns an estimate of the memory retained by the interner in bytes.
+    ///
+    /// This includes the tree nodes and the bytes of each value, but not any
+    /// spare capacity held by the values themselves.
+    pub fn memory_usage(&self) -> usize
+    where
+        T: AsRef<[u8]>,
+    {
+        let nodes = self.value_to_pos.len() * size_of::<(T, Option<usize>)>();
+        let values = self
+            .value_to_pos
+            .keys()
+            .map(|value| value.as_ref().len())
+            .sum::<usize>();
+        nodes + values
+    }
+
+    /// Removes all values from the interner.
+    pub fn clear(&mut self) {
+        self.value_to_pos.clear();
+    }
+}
+
+impl<T> Default for BTreeInterner<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, E> Interning<T, E> for BTreeInterner<T::Owned>
+where
+    T::Owned: Ord + Borrow<T>,
+    T: Ord + ToOwned + ?Sized,
+    E: Source,
+{
+    fn start_interning(&mut self, value: &T) -> SharingState {
+        match self.value_to_pos.get(value) {
+            None => {
+                self.value_to_pos.insert(value.to_owned(), None);
+                SharingState::Started
+            }
+            Some(None) => SharingState::Pending,
+            Some(Some(pos)) => SharingState::Finished(*pos),
+        }
+    }
+
+    fn finish_interning(&mut self, value: &T, pos: usize) -> Result<(), E> {
+        match self.value_to_pos.get_mut(value) {
+            None => fail!(NotStarted),
+            Some(Some(existing)) => fail!(AlreadyFinished {
+                value: None,
+                existing: *existing,
+                new: pos,
+            }),
+            Some(x) => {
+                *x = Some(pos);
+                Ok(())
+            }
+        }
+    }
+
+    fn contains(&self, value: &T) -> bool {
+        self.value_to_pos.contains_key(value)
+    }
+
+    fn len(&self) -> usize {
+        self.value_to_pos.len()
+    }
+}
@@ -0,0 +1,263 @@
+use alloc::collections::{BTreeSet, BinaryHeap, LinkedList, VecDeque};
+#[cfg(feature = "hashbrown")]
+use core::hash::{BuildHasher, Hash};
+use core::marker::PhantomData;
+
+#[cfg(feature = "hashbrown")]
+use hashbrown::HashSet;
+use rkyv::{
+    munge::munge,
+    rancor::Fallible,
+    ser::{Allocator, Writer},
+    tuple::{
+        ArchivedTuple1, ArchivedTuple2, ArchivedTuple3, ArchivedTuple4,
+        ArchivedTuple5, ArchivedTuple6, ArchivedTuple7, ArchivedTuple8,
+    },
+    vec::{ArchivedVec, VecResolver},
+    with::{ArchiveWith, DeserializeWith, SerializeWith},
+    Archive, Place, Serialize,
+};
+
+use crate::Intern;
+
+/// A wrapper which applies `W` to each element of a collection.
+///
+/// rkyv's `Map` only reaches the elements of `Vec`, `Option`, and arrays. This
+/// covers the other standard collections: `VecDeque`, `LinkedList`,
+/// `BinaryHeap`, `BTreeSet`, and hashbrown's `HashSet`. The collection is
+/// archived as a vector of its elements in iteration order, and collected
+/// back into the same collection when deserialized.
+///
+/// Tuples of up to eight elements are also supported. `W` is applied to each
+/// element and the tuple keeps its usual archived layout, so with [`Intern`],
+/// both elements of a `(String, String)` are interned.
+///
+/// # Example
+#[cfg_attr(feature = "hashbrown", doc = "```")]
+#[cfg_attr(not(feature = "hashbrown"), doc = "```ignore")]
+/// use std::collections::{BTreeSet, VecDeque};
+///
+/// use rkyv::Archive;
+/// use rkyv_intern::{Intern, InternEach};
+///
+/// #[derive(Archive, rkyv::Serialize)]
+/// struct Record {
+///     #[rkyv(with = InternEach<Intern>)]
+///     history: VecDeque<String>,
+///     #[rkyv(with = InternEach<Intern>)]
+///     tags: BTreeSet<String>,
+/// }
+/// ```
+pub struct InternEach<W = Intern> {
+    _phantom: PhantomData<W>,
+}
+
+struct Element<'a, W, O>(&'a O, PhantomData<W>);
+
+impl<W, O> Clone for Element<'_, W, O> {
+    fn clone(&self) -> Self {
+        Self(self.0, PhantomData)
+    }
+}
+
+impl<W: ArchiveWith<O>, O> Archive for Element<'_, W, O> {
+    type Archived = W::Archived;
+    type Resolver = W::Resolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        W::resolve_with(self.0, resolver, out)
+    }
+}
+
+impl<W, O, S> Serialize<S> for Element<'_, W, O>
+where
+    W: SerializeWith<O, S>,
+    S: Fallible + ?Sized,
+{
+    fn serialize(
+        &self,
+        serializer: &mut S,
+    ) -> Result<Self::Resolver, S::Error> {
+        W::serialize_with(self.0, serializer)
+    }
+}
+
+macro_rules! impl_intern_each {
+    ($($ty:ident<O $(, $param:ident)*> $(where [$($bound:tt)*])?;)*) => {
+        $(
+            impl<W, O $(, $param)*> ArchiveWith<$ty<O $(, $param)*>>
+                for InternEach<W>
+            where
+                W: ArchiveWith<O>,
+            {
+                type Archived = ArchivedVec<W::Archived>;
+                type Resolver = VecResolver;
+
+                fn resolve_with(
+                    field: &$ty<O $(, $param)*>,
+                    resolver: Self::Resolver,
+                    out: Place<Self::Archived>,
+                ) {
+                    ArchivedVec::resolve_from_len(field.len(), resolver, out)
+                }
+            }
+
+            impl<W, O $(, $param)*, S> SerializeWith<$ty<O $(, $param)*>, S>
+                for InternEach<W>
+            where
+                W: SerializeWith<O, S>,
+                S: Fallible + Allocator + Writer + ?Sized,
+            {
+                fn serialize_with(
+                    field: &$ty<O $(, $param)*>,
+                    serializer: &mut S,
+                ) -> Result<Self::Resolver, S::Error> {
+                    let elements = field
+                        .iter()
+                        .map(|value| Element::<W, O>(value, PhantomData));
+                    ArchivedVec::<W::Archived>::serialize_from_iter::<
+                        Element<'_, W, O>,
+                        _,
+                        _,
+                    >(elements, serializer)
+                }
+            }
+
+            impl<W, O $(, $param)*, D> DeserializeWith<
+                ArchivedVec<W::Archived>,
+                $ty<O $(, $param)*>,
+                D,
+            > for InternEach<W>
+            where
+                W: ArchiveWith<O> + DeserializeWith<W::Archived, O, D>,
+                D: Fallible + ?Sized,
+                $($($bound)*)?
+            {
+                fn deserialize_with(
+                    field: &ArchivedVec<W::Archived>,
+                    deserializer: &mut D,
+                ) -> Result<$ty<O $(, $param)*>, D::Error> {
+                    field
+                        .iter()
+                        .map(|value| W::deserialize_with(value, deserializer))
+                        .collect()
+                }
+            }
+        )*
+    };
+}
+
+impl_intern_each! {
+    VecDeque<O>;
+    LinkedList<O>;
+    BinaryHeap<O> where [O: Ord];
+    BTreeSet<O> where [O: Ord];
+}
+
+#[cfg(feature = "hashbrown")]
+impl_intern_each! {
+    HashSet<O, H> where [O: Hash + Eq, H: BuildHasher + Default];
+}
+
+macro_rules! impl_intern_each_tuple {
+    ($name:ident, $($type:ident $out:ident $index:tt),*) => {
+        impl<W, $($type),*> ArchiveWith<($($type,)*)> for InternEach<W>
+        where
+            $(W: ArchiveWith<$type>,)*
+        {
+            type Archived = $name<$(<W as ArchiveWith<$type>>::Archived,)*>;
+            type Resolver = ($(<W as ArchiveWith<$type>>::Resolver,)*);
+
+            fn resolve_with(
+                field: &($($type,)*),
+                resolver: Self::Resolver,
+                out: Place<Self::Archived>,
+            ) {
+                munge!(let $name($($out,)*) = out);
+                $(
+                    <W as ArchiveWith<$type>>::resolve_with(
+                        &field.$index,
+                        resolver.$index,
+                        $out,
+                    );
+                )*
+            }
+        }
+
+        impl<W, $($type,)* S> SerializeWith<($($type,)*), S> for InternEach<W>
+        where
+            $(W: SerializeWith<$type, S>,)*
+            S: Fallible + ?Sized,
+        {
+            fn serialize_with(
+                field: &($($type,)*),
+                serializer: &mut S,
+            ) -> Result<Self::Resolver, S::Error> {
+                Ok((
+                    $(
+                        <W as SerializeWith<$type, S>>::serialize_with(
+                            &field.$index,
+                            serializer,
+                        )?,
+                    )*
+                ))
+            }
+        }
+
+        impl<W, $($type,)* D> DeserializeWith<
+            $name<$(<W as ArchiveWith<$type>>::Archived,)*>,
+            ($($type,)*),
+            D,
+        > for InternEach<W>
+        where
+            $(
+                W: ArchiveWith<$type>
+                    + DeserializeWith<
+                        <W as ArchiveWith<$type>>::Archived,
+                        $type,
+                        D,
+                    >,
+            )*
+            D: Fallible + ?Sized,
+        {
+            fn deserialize_with(
+                field: &$name<$(<W as ArchiveWith<$type>>::Archived,)*>,
+                deserializer: &mut D,
+            ) -> Result<($($type,)*), D::Error> {
+                Ok((
+                    $(
+                        <W as DeserializeWith<
+                            <W as ArchiveWith<$type>>::Archived,
+                            $type,
+                            D,
+                        >>::deserialize_with(&field.$index, deserializer)?,
+                    )*
+                ))
+            }
+        }
+    };
+}
+
+impl_intern_each_tuple!(ArchivedTuple1, T0 out0 0);
+impl_intern_each_tuple!(ArchivedTuple2, T0 out0 0, T1 out1 1);
+impl_intern_each_tuple!(ArchivedTuple3, T0 out0 0, T1 out1 1, T2 out2 2);
+impl_intern_each_tuple!(
+    ArchivedTuple4, T0 out0 0, T1 out1 1, T2 out2 2, T3 out3 3
+);
+impl_intern_each_tuple!(
+    ArchivedTuple5, T0 out0 0, T1 out1 1, T2 out2 2, T3 out3 3, T4 out4 4
+);
+impl_intern_each_tuple!(
+    ArchivedTuple6,
+    T0 out0 0, T1 out1 1, T2 out2 2, T3 out3 3, T4 out4 4, T5 out5 5
+);
+impl_intern_each_tuple!(
+    ArchivedTuple7,
+    T0 out0 0, T1 out1 1, T2 out2 2, T3 out3 3, T4 out4 4, T5 out5 5,
+    T6 out6 6
+);
+impl_intern_each_tuple!(
+    ArchivedTuple8,
+    T0 out0 0, T1 out1 1, T2 out2 2, T3 out3 3, T4 out4 4, T5 out5 5,
+    T6 out6 6, T7 out7 7
+);
@@ -0,0 +1,464 @@
+#[cfg(feature = "hashbrown")]
+use core::{
+    hash::{BuildHasher, BuildHasherDefault, Hash, Hasher},
+    mem::size_of,
+};
+
+#[cfg(feature = "hashbrown")]
+use hashbrown::{hash_map::DefaultHashBuilder, HashMap};
+use rkyv::ser::sharing::SharingState;
+
+use crate::{ByteSize, Interning};
+
+/// An interning adapter which only interns values of at least a minimum size.
+///
+/// Smaller values are serialized every time they are encountered instead of
+/// being interned, which avoids the bookkeeping for values where
+/// deduplication saves little. Sizes are measured with [`ByteSize`], which is
+/// the byte length for strings and byte slices.
+///
+/// # Example
+#[cfg_attr(feature = "hashbrown", doc = "```")]
+#[cfg_attr(not(feature = "hashbrown"), doc = "```ignore")]
+/// use rkyv_intern::{Interner, MinSize};
+///
+/// // Only intern strings which are at least 32 bytes long.
+/// let interning = MinSize::new(Interner::<String>::new(), 32);
+/// ```
+#[derive(Debug)]
+pub struct MinSize<I> {
+    interning: I,
+    min_size: usize,
+}
+
+impl<I> MinSize<I> {
+    /// Returns a new adapter which only interns values of at least `min_size`
+    /// bytes.
+    pub fn new(interning: I, min_size: usize) -> Self {
+        Self {
+            interning,
+            min_size,
+        }
+    }
+
+    /// Consumes the adapter and returns the underlying interning.
+    pub fn into_inner(self) -> I {
+        self.interning
+    }
+}
+
+impl<I, T, E> Interning<T, E> for MinSize<I>
+where
+    I: Interning<T, E>,
+    T: ByteSize + ?Sized,
+{
+    fn start_interning(&mut self, value: &T) -> SharingState {
+        if value.byte_size() < self.min_size {
+            SharingState::Started
+        } else {
+            self.interning.start_interning(value)
+        }
+    }
+
+    fn finish_interning(&mut self, value: &T, pos: usize) -> Result<(), E> {
+        if value.byte_size() < self.min_size {
+            Ok(())
+        } else {
+            self.interning.finish_interning(value, pos)
+        }
+    }
+
+    fn contains(&self, value: &T) -> bool {
+        value.byte_size() >= self.min_size && self.interning.contains(value)
+    }
+
+    fn len(&self) -> usize {
+        self.interning.len()
+    }
+}
+
+/// An interning adapter which only interns values of at most a maximum size.
+///
+/// Larger values are serialized every time they are encountered instead of
+/// being interned, so the underlying interning never retains copies of them.
+/// Sizes are measured the same way as for [`MinSize`].
+///
+/// # Example
+#[cfg_attr(feature = "hashbrown", doc = "```")]
+#[cfg_attr(not(feature = "hashbrown"), doc = "```ignore")]
+/// use rkyv_intern::{Interner, MaxSize};
+///
+/// // Don't retain copies of strings larger than 64 KiB.
+/// let interning = MaxSize::new(Interner::<String>::new(), 64 * 1024);
+/// ```
+#[derive(Debug)]
+pub struct MaxSize<I> {
+    interning: I,
+    max_size: usize,
+}
+
+impl<I> MaxSize<I> {
+    /// Returns a new adapter which only interns values of at most `max_size`
+    /// bytes.
+    pub fn new(interning: I, max_size: usize) -> Self {
+        Self {
+            interning,
+            max_size,
+        }
+    }
+
+    /// Consumes the adapter and returns the underlying interning.
+    pub fn into_inner(self) -> I {
+        self.interning
+    }
+}
+
+impl<I, T, E> Interning<T, E> for MaxSize<I>
+where
+    I: Interning<T, E>,
+    T: ByteSize + ?Sized,
+{
+    fn start_interning(&mut self, value: &T) -> SharingState {
+        if value.byte_size() > self.max_size {
+            SharingState::Started
+        } else {
+            self.interning.start_interning(value)
+        }
+    }
+
+    fn finish_interning(&mut self, value: &T, pos: usize) -> Result<(), E> {
+        if value.byte_size() > self.max_size {
+            Ok(())
+        } else {
+            self.interning.finish_interning(value, pos)
+        }
+    }
+
+    fn contains(&self, value: &T) -> bool {
+        value.byte_size() <= self.max_size && self.interning.contains(value)
+    }
+
+    fn len(&self) -> usize {
+        self.interning.len()
+    }
+}
+
+/// An interning adapter which stops interning new values once the underlying
+/// interning holds a maximum number of entries.
+///
+/// Values which are already interned are still shared, and new values are
+/// serialized every time they are encountered. This bounds the memory used
+/// for pathological inputs without failing serialization.
+///
+/// # Example
+#[cfg_attr(feature = "hashbrown", doc = "```")]
+#[cfg_attr(not(feature = "hashbrown"), doc = "```ignore")]
+/// use rkyv_intern::{Interner, MaxEntries};
+///
+/// // Intern at most 10,000 distinct strings.
+/// let interning = MaxEntries::new(Interner::<String>::new(), 10_000);
+/// ```
+#[derive(Debug)]
+pub struct MaxEntries<I> {
+    interning: I,
+    max_entries: usize,
+}
+
+impl<I> MaxEntries<I> {
+    /// Returns a new adapter which stops interning new values once the
+    /// underlying interning holds `max_entries` values.
+    pub fn new(interning: I, max_entries: usize) -> Self {
+        Self {
+            interning,
+            max_entries,
+        }
+    }
+
+    /// Consumes the adapter and returns the underlying interning.
+    pub fn into_inner(self) -> I {
+        self.interning
+    }
+
+    fn accepts<T, E>(&self, value: &T) -> bool
+    where
+        I: Interning<T, E>,
+        T: ?Sized,
+    {
+        self.interning.len() < self.max_entries
+            || self.interning.contains(value)
+    }
+}
+
+impl<I, T, E> Interning<T, E> for MaxEntries<I>
+where
+    I: Interning<T, E>,
+    T: ?Sized,
+{
+    fn start_interning(&mut self, value: &T) -> SharingState {
+        if self.accepts(value) {
+            self.interning.start_interning(value)
+        } else {
+            SharingState::Started
+        }
+    }
+
+    fn finish_interning(&mut self, value: &T, pos: usize) -> Result<(), E> {
+        if self.accepts(value) {
+            self.interning.finish_interning(value, pos)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn contains(&self, value: &T) -> bool {
+        self.interning.contains(value)
+    }
+
+    fn len(&self) -> usize {
+        self.interning.len()
+    }
+}
+
+/// An interning adapter which only interns a random sample of values.
+///
+/// A sample is drawn each time a value is encountered. Sampled values go
+/// through the underlying interning, and the rest are serialized without being
+/// looked up at all. This bounds hashing to roughly one in `rate` values:
+/// frequent values are likely to be interned early and shared whenever they
+/// are sampled again, while most rare values are never retained. Samples are
+/// drawn from a pseudorandom generator seeded with `seed`, so the same input
+/// and seed always produce the same output.
+///
+/// # Example
+#[cfg_attr(feature = "hashbrown", doc = "```")]
+#[cfg_attr(not(feature = "hashbrown"), doc = "```ignore")]
+/// use rkyv_intern::{Interner, Sample};
+///
+/// // Intern roughly one in eight strings.
+/// let interning = Sample::new(Interner::<String>::new(), 8, 0x5eed);
+/// ```
+#[derive(Debug)]
+pub struct Sample<I> {
+    interning: I,
+    rate: u64,
+    state: u64,
+    depth: usize,
+    sampled: u64,
+}
+
+impl<I> Sample<I> {
+    /// Returns a new adapter which interns one in `rate` values, drawing
+    /// samples from a generator seeded with `seed`.
+    ///
+    /// A `rate` of zero or one interns every value.
+    pub fn new(interning: I, rate: u64, seed: u64) -> Self {
+        // Xorshift gets stuck at zero, so replace a zero seed.
+        let state = if seed == 0 {
+            0x9e37_79b9_7f4a_7c15
+        } else {
+            seed
+        };
+        Self {
+            interning,
+            rate: rate.max(1),
+            state,
+            depth: 0,
+            sampled: 0,
+        }
+    }
+
+    /// Consumes the adapter and returns the underlying interning.
+    pub fn into_inner(self) -> I {
+        self.interning
+    }
+
+    fn sample(&mut self) -> bool {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x % self.rate == 0
+    }
+}
+
+impl<I, T, E> Interning<T, E> for Sample<I>
+where
+    I: Interning<T, E>,
+    T: ?Sized,
+{
+    fn start_interning(&mut self, value: &T) -> SharingState {
+        // Whether each started value was sampled is kept as a stack of bits,
+        // so values nested deeper than that are never sampled.
+        let tracked = self.depth < u64::BITS as usize;
+        let sampled = tracked && self.sample();
+        let state = if sampled {
+            self.interning.start_interning(value)
+        } else {
+            SharingState::Started
+        };
+        if let SharingState::Started = state {
+            if tracked {
+                let bit = 1 << self.depth;
+                if sampled {
+                    self.sampled |= bit;
+                } else {
+                    self.sampled &= !bit;
+                }
+            }
+            self.depth += 1;
+        }
+        state
+    }
+
+    fn finish_interning(&mut self, value: &T, pos: usize) -> Result<(), E> {
+        if self.depth == 0 {
+            return self.interning.finish_interning(value, pos);
+        }
+        self.depth -= 1;
+        let tracked = self.depth < u64::BITS as usize;
+        if tracked && self.sampled & (1 << self.depth) != 0 {
+            self.interning.finish_interning(value, pos)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn contains(&self, value: &T) -> bool {
+        self.interning.contains(value)
+    }
+
+    fn len(&self) -> usize {
+        self.interning.len()
+    }
+}
+
+/// An interning adapter which only interns values once they have been
+/// encountered a minimum number of times.
+///
+/// Until a value has been seen `min_occurrences` times, it is serialized every
+/// time it is encountered. This keeps one-off values out of the underlying
+/// interning. Occurrences are counted by hash, so no copies of the values are
+/// retained; distinct values whose hashes collide share a count, which can only
+/// make them interned sooner.
+///
+/// # Example
+///
+/// ```
+/// use rkyv_intern::{Interner, MinOccurrences};
+///
+/// // Only intern strings which occur at least three times.
+/// let interning = MinOccurrences::new(Interner::<String>::new(), 3);
+/// ```
+#[cfg(feature = "hashbrown")]
+#[derive(Debug)]
+pub struct MinOccurrences<I, S = DefaultHashBuilder> {
+    interning: I,
+    hash_builder: S,
+    occurrences: HashMap<u64, usize, BuildHasherDefault<HashHasher>>,
+    min_occurrences: usize,
+}
+
+#[cfg(feature = "hashbrown")]
+impl<I> MinOccurrences<I> {
+    /// Returns a new adapter which only interns values once they have been
+    /// encountered `min_occurrences` times.
+    pub fn new(interning: I, min_occurrences: usize) -> Self {
+        Self::with_hasher(
+            interning,
+            min_occurrences,
+            DefaultHashBuilder::default(),
+        )
+    }
+}
+
+#[cfg(feature = "hashbrown")]
+impl<I, S> MinOccurrences<I, S> {
+    /// Returns a new adapter which only interns values once they have been
+    /// encountered `min_occurrences` times, and uses the given hash builder to
+    /// hash them.
+    pub fn with_hasher(
+        interning: I,
+        min_occurrences: usize,
+        hash_builder: S,
+    ) -> Self {
+        Self {
+            interning,
+            hash_builder,
+            occurrences: HashMap::default(),
+            min_occurrences,
+        }
+    }
+
+    /// Returns the approximate number of bytes used to count occurrences.
+    ///
+    /// This does not include the memory used by the underlying interning.
+    pub fn memory_usage(&self) -> usize {
+        self.occurrences.capacity() * size_of::<(u64, usize)>()
+    }
+
+    /// Consumes the adapter and returns the underlying interning.
+    pub fn into_inner(self) -> I {
+        self.interning
+    }
+}
+
+#[cfg(feature = "hashbrown")]
+impl<I, T, S, E> Interning<T, E> for MinOccurrences<I, S>
+where
+    I: Interning<T, E>,
+    T: Hash + ?Sized,
+    S: BuildHasher,
+{
+    fn start_interning(&mut self, value: &T) -> SharingState {
+        let hash = self.hash_builder.hash_one(value);
+        let occurrences = self.occurrences.entry(hash).or_insert(0);
+        *occurrences = occurrences.saturating_add(1);
+
+        if *occurrences < self.min_occurrences {
+            SharingState::Started
+        } else {
+            self.interning.start_interning(value)
+        }
+    }
+
+    fn finish_interning(&mut self, value: &T, pos: usize) -> Result<(), E> {
+        let hash = self.hash_builder.hash_one(value);
+        match self.occurrences.get(&hash) {
+            Some(occurrences) if *occurrences >= self.min_occurrences => {
+                self.interning.finish_interning(value, pos)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn contains(&self, value: &T) -> bool {
+        self.interning.contains(value)
+    }
+
+    fn len(&self) -> usize {
+        self.interning.len()
+    }
+}
+
+/// A hasher for keys which are already hashes.
+#[cfg(feature = "hashbrown")]
+#[derive(Debug, Default)]
+struct HashHasher(u64);
+
+#[cfg(feature = "hashbrown")]
+impl Hasher for HashHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = self.0.rotate_left(8) ^ u64::from(byte);
+        }
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.0 = n;
+    }
+}
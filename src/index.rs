@@ -0,0 +1,153 @@
+use alloc::{borrow::ToOwned, vec::Vec};
+use core::{
+    borrow::Borrow,
+    hash::{BuildHasher, Hash},
+    mem::size_of,
+};
+
+use hashbrown::hash_map::DefaultHashBuilder;
+use indexmap::{
+    map::{raw_entry_v1::RawEntryMut, RawEntryApiV1 as _},
+    IndexMap,
+};
+use rkyv::{
+    rancor::{fail, Source},
+    ser::sharing::SharingState,
+};
+
+use crate::{AlreadyFinished, Interning, NotStarted};
+
+/// A value interner which remembers the order values were first interned in.
+///
+/// Iterating or exporting the interner yields values in first-occurrence
+/// order, so dictionaries built from it are stable across runs.
+pub struct IndexInterner<T, S = DefaultHashBuilder> {
+    value_to_pos: IndexMap<T, Option<usize>, S>,
+}
+
+impl<T> IndexInterner<T> {
+    /// Returns a new, empty interner.
+    pub fn new() -> Self {
+        Self::with_hasher(DefaultHashBuilder::default())
+    }
+}
+
+impl<T, S> IndexInterner<T, S> {
+    /// Returns a new, empty interner which uses the given hash builder to hash
+    /// values.
+    pub fn with_hasher(hash_builder: S) -> Self {
+        Self {
+            value_to_pos: IndexMap::with_hasher(hash_builder),
+        }
+    }
+
+    /// Returns the number of values in the interner.
+    pub fn len(&self) -> usize {
+        self.value_to_pos.len()
+    }
+
+    /// Returns whether the interner is empty.
+    pub fn is_empty(&self) -> bool {
+        self.value_to_pos.is_empty()
+    }
+
+    /// Returns an estimate of the memory retained by the interner in bytes.
+    ///
+    /// This includes the table allocation and the bytes of each value, but
+    /// not any spare capacity held by the values themselves.
+    pub fn memory_usage(&self) -> usize
+    where
+        T: AsRef<[u8]>,
+    {
+        let table =
+            self.value_to_pos.capacity() * size_of::<(T, Option<usize>)>();
+        let values = self
+            .value_to_pos
+            .keys()
+            .map(|value| value.as_ref().len())
+            .sum::<usize>();
+        table + values
+    }
+
+    /// Removes all values from the interner while keeping its allocated
+    /// capacity.
+    pub fn clear(&mut self) {
+        self.value_to_pos.clear();
+    }
+
+    /// Returns an iterator over the interned values and their positions in
+    /// the order they were first interned.
+    ///
+    /// Values which were started but never finished are skipped.
+    pub fn iter(&self) -> impl Iterator<Item = (&T, usize)> + '_ {
+        self.value_to_pos
+            .iter()
+            .filter_map(|(value, pos)| Some((value, (*pos)?)))
+    }
+
+    /// Exports the finished entries of the interner as `(value, position)`
+    /// pairs in the order they were first interned.
+    pub fn export(&self) -> Vec<(T, usize)>
+    where
+        T: Clone,
+    {
+        self.iter()
+            .map(|(value, pos)| (value.clone(), pos))
+            .collect()
+    }
+}
+
+impl<T, S: Default> Default for IndexInterner<T, S> {
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<T, S, E> Interning<T, E> for IndexInterner<T::Owned, S>
+where
+    T::Owned: Hash + Eq + Borrow<T>,
+    T: Hash + Eq + ToOwned + ?Sized,
+    S: BuildHasher,
+    E: Source,
+{
+    fn start_interning(&mut self, value: &T) -> SharingState {
+        let hash = self.value_to_pos.hasher().hash_one(value);
+        match self
+            .value_to_pos
+            .raw_entry_mut_v1()
+            .from_key_hashed_nocheck(hash, value)
+        {
+            RawEntryMut::Vacant(vacant) => {
+                vacant.insert_hashed_nocheck(hash, value.to_owned(), None);
+                SharingState::Started
+            }
+            RawEntryMut::Occupied(occupied) => match occupied.get() {
+                None => SharingState::Pending,
+                Some(pos) => SharingState::Finished(*pos),
+            },
+        }
+    }
+
+    fn finish_interning(&mut self, value: &T, pos: usize) -> Result<(), E> {
+        match self.value_to_pos.get_mut(value) {
+            None => fail!(NotStarted),
+            Some(Some(existing)) => fail!(AlreadyFinished {
+                value: None,
+                existing: *existing,
+                new: pos,
+            }),
+            Some(x) => {
+                *x = Some(pos);
+                Ok(())
+            }
+        }
+    }
+
+    fn contains(&self, value: &T) -> bool {
+        self.value_to_pos.contains_key(value)
+    }
+
+    fn len(&self) -> usize {
+        self.value_to_pos.len()
+    }
+}
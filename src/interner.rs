@@ -1,81 +1,741 @@
-use alloc::borrow::ToOwned;
-use core::{borrow::Borrow, error::Error, fmt, hash::Hash};
+use alloc::{borrow::ToOwned, format, string::String, vec::Vec};
+use core::{
+    borrow::Borrow,
+    cmp::Reverse,
+    error::Error,
+    fmt::{self, Debug},
+    hash::{BuildHasher, Hash},
+    mem::size_of,
+};
 
-use hashbrown::HashMap;
+use hashbrown::{
+    hash_map::{DefaultHashBuilder, RawEntryMut},
+    HashMap,
+};
 use rkyv::{
     rancor::{fail, Source},
     ser::sharing::SharingState,
 };
 
-use crate::Interning;
+use crate::{AlreadyFinished, ByteSize, Interning, NotStarted};
+
+fn debug_string<T: Debug>(value: &T) -> String {
+    format!("{:?}", value)
+}
+
+struct Entry {
+    pos: Option<usize>,
+    seq: usize,
+    size: usize,
+    refs: usize,
+}
+
+/// What an [`Interner`] does with new values once its memory budget is
+/// exhausted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BudgetPolicy {
+    /// Fail serialization with an error.
+    Error,
+    /// Serialize new values without interning them.
+    Fallback,
+}
+
+/// What an [`Interner`] does when a value is finished interning again after it
+/// was already finished.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Fail serialization with an error.
+    Error,
+    /// Keep the position the value was first finished at.
+    KeepFirst,
+    /// Replace the position with the new one.
+    Overwrite,
+}
+
+struct Budget<T> {
+    max_bytes: usize,
+    policy: BudgetPolicy,
+    size_of: fn(&T) -> usize,
+}
+
+impl<T> Budget<T> {
+    fn exceeded_by(&self, retained: usize, size: usize) -> bool {
+        retained + size > self.max_bytes
+    }
+}
+
+/// The state of an [`Interner`] at some point during serialization.
+///
+/// Returned by [`Interner::snapshot`] and restored with
+/// [`Interner::rollback`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InternerSnapshot {
+    seq: usize,
+    scopes: usize,
+}
 
 /// A general-purpose value interner.
-pub struct Interner<T> {
-    value_to_pos: HashMap<T, Option<usize>>,
+///
+/// Values are hashed with `S`, which defaults to hashbrown's default hasher.
+/// On targets with an operating system, its keys are drawn from the system's
+/// random number generator when the program starts. On other targets, the
+/// keys are fixed unless the `compile-time-rng` feature is enabled, which
+/// generates them when the crate is built. When interning untrusted input on
+/// those targets, pass a keyed hasher to [`with_hasher`](Self::with_hasher).
+pub struct Interner<T, S = DefaultHashBuilder> {
+    value_to_entry: HashMap<T, Entry, S>,
+    started: Vec<u64>,
+    next_seq: usize,
+    scopes: Vec<usize>,
+    budget: Option<Budget<T>>,
+    retained: usize,
+    duplicates: DuplicatePolicy,
+    debug_values: Option<fn(&T) -> String>,
 }
 
 impl<T> Interner<T> {
     /// Returns a new, empty interner.
     pub fn new() -> Self {
         Self {
-            value_to_pos: HashMap::new(),
+            value_to_entry: HashMap::new(),
+            started: Vec::new(),
+            next_seq: 0,
+            scopes: Vec::new(),
+            budget: None,
+            retained: 0,
+            duplicates: DuplicatePolicy::Error,
+            debug_values: None,
         }
     }
 }
 
-impl<T> Default for Interner<T> {
+impl<T, S> Interner<T, S> {
+    /// Returns a new, empty interner which uses the given hash builder to hash
+    /// values.
+    pub fn with_hasher(hash_builder: S) -> Self {
+        Self {
+            value_to_entry: HashMap::with_hasher(hash_builder),
+            started: Vec::new(),
+            next_seq: 0,
+            scopes: Vec::new(),
+            budget: None,
+            retained: 0,
+            duplicates: DuplicatePolicy::Error,
+            debug_values: None,
+        }
+    }
+
+    /// Limits the total size of the values retained by the interner.
+    ///
+    /// Once interning a new value would retain more than `max_bytes`, the
+    /// interner either fails or stops interning new values, depending on
+    /// `policy`. Values which are already interned are still shared. Sizes
+    /// are measured with [`ByteSize`], which is the byte length for strings
+    /// and byte slices. Imported entries don't count against the budget.
+    pub fn set_budget(&mut self, max_bytes: usize, policy: BudgetPolicy)
+    where
+        T: ByteSize,
+    {
+        self.budget = Some(Budget {
+            max_bytes,
+            policy,
+            size_of: T::byte_size,
+        });
+    }
+
+    /// Sets what the interner does when a value is finished interning again
+    /// after it was already finished.
+    ///
+    /// By default, this is an error.
+    pub fn set_duplicate_policy(&mut self, policy: DuplicatePolicy) {
+        self.duplicates = policy;
+    }
+
+    /// Includes the [`Debug`] representation of a value in the error returned
+    /// when it is finished interning again.
+    pub fn set_debug_values(&mut self)
+    where
+        T: Debug,
+    {
+        self.debug_values = Some(debug_string::<T>);
+    }
+
+    /// Returns the total size of the values retained by the interner.
+    ///
+    /// Sizes are only measured while a budget is set.
+    pub fn retained_bytes(&self) -> usize {
+        self.retained
+    }
+
+    /// Returns the number of values in the interner.
+    pub fn len(&self) -> usize {
+        self.value_to_entry.len()
+    }
+
+    /// Returns whether the interner is empty.
+    pub fn is_empty(&self) -> bool {
+        self.value_to_entry.is_empty()
+    }
+
+    /// Returns an estimate of the memory retained by the interner in bytes.
+    ///
+    /// This includes the table allocation and the bytes of each value, but
+    /// not any spare capacity held by the values themselves.
+    pub fn memory_usage(&self) -> usize
+    where
+        T: AsRef<[u8]>,
+    {
+        let table = self.value_to_entry.capacity() * size_of::<(T, Entry)>();
+        let values = self
+            .value_to_entry
+            .keys()
+            .map(|value| value.as_ref().len())
+            .sum::<usize>();
+        table + values
+    }
+
+    /// Removes all values from the interner while keeping its allocated
+    /// capacity.
+    ///
+    /// This also closes any open scopes.
+    pub fn clear(&mut self) {
+        self.value_to_entry.clear();
+        self.started.clear();
+        self.next_seq = 0;
+        self.scopes.clear();
+        self.retained = 0;
+    }
+
+    /// Opens a new scope.
+    ///
+    /// Values interned while the scope is open are removed when it is closed
+    /// with [`pop_scope`](Self::pop_scope). Values interned in enclosing scopes
+    /// are still shared inside of it.
+    pub fn push_scope(&mut self) {
+        self.scopes.push(self.next_seq);
+    }
+
+    /// Closes the innermost scope, removing every value interned since it was
+    /// opened.
+    ///
+    /// Returns `false` if there was no open scope.
+    pub fn pop_scope(&mut self) -> bool {
+        match self.scopes.pop() {
+            Some(start) => {
+                self.remove_since(start);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns a snapshot of the interner which can later be restored with
+    /// [`rollback`](Self::rollback).
+    pub fn snapshot(&self) -> InternerSnapshot {
+        InternerSnapshot {
+            seq: self.next_seq,
+            scopes: self.scopes.len(),
+        }
+    }
+
+    /// Restores the interner to a snapshot, removing every value interned
+    /// since it was taken.
+    ///
+    /// This is intended for speculatively serializing a value: if
+    /// serialization fails and the output is truncated back to where it was,
+    /// rolling back ensures that later values don't refer to positions which
+    /// were never committed. Scopes opened since the snapshot are closed.
+    /// Values which were pending when the snapshot was taken are kept.
+    pub fn rollback(&mut self, snapshot: InternerSnapshot) {
+        self.scopes.truncate(snapshot.scopes);
+        self.remove_since(snapshot.seq);
+        self.started.clear();
+        self.next_seq = snapshot.seq;
+    }
+
+    fn remove_since(&mut self, start: usize) {
+        let retained = &mut self.retained;
+        self.value_to_entry.retain(|_, entry| {
+            let keep = entry.seq < start;
+            if !keep {
+                *retained -= entry.size;
+            }
+            keep
+        });
+    }
+
+    /// Returns an iterator over the interned values and their positions.
+    ///
+    /// Values which were started but never finished are skipped.
+    pub fn iter(&self) -> impl Iterator<Item = (&T, usize)> + '_ {
+        self.value_to_entry
+            .iter()
+            .filter_map(|(value, entry)| Some((value, entry.pos?)))
+    }
+
+    /// Returns the `n` most referenced values and how many times each was
+    /// referenced, starting with the most referenced.
+    ///
+    /// Every time a value is serialized with the interner counts as a
+    /// reference, including the first. Imported entries start with no
+    /// references.
+    pub fn most_referenced(&self, n: usize) -> Vec<(&T, usize)> {
+        let mut refs = self
+            .value_to_entry
+            .iter()
+            .map(|(value, entry)| (value, entry.refs))
+            .collect::<Vec<_>>();
+        refs.sort_unstable_by_key(|&(_, refs)| Reverse(refs));
+        refs.truncate(n);
+        refs
+    }
+
+    /// Returns an iterator over the values which were started but never
+    /// finished.
+    ///
+    /// Outside of serialization, pending values indicate that serializing one
+    /// of them failed partway through. Starting them again reports a cyclic
+    /// value error, so they should be removed with
+    /// [`remove_pending`](Self::remove_pending) before the interner is reused.
+    pub fn pending(&self) -> impl Iterator<Item = &T> + '_ {
+        self.value_to_entry
+            .iter()
+            .filter(|(_, entry)| entry.pos.is_none())
+            .map(|(value, _)| value)
+    }
+
+    /// Removes every value which was started but never finished.
+    ///
+    /// Returns the number of values removed.
+    pub fn remove_pending(&mut self) -> usize {
+        self.started.clear();
+        let len = self.value_to_entry.len();
+        let retained = &mut self.retained;
+        self.value_to_entry.retain(|_, entry| {
+            let keep = entry.pos.is_some();
+            if !keep {
+                *retained -= entry.size;
+            }
+            keep
+        });
+        len - self.value_to_entry.len()
+    }
+
+    /// Exports the finished entries of the interner as `(value, position)`
+    /// pairs.
+    pub fn export(&self) -> Vec<(T, usize)>
+    where
+        T: Clone,
+    {
+        self.iter()
+            .map(|(value, pos)| (value.clone(), pos))
+            .collect()
+    }
+
+    /// Imports `(value, position)` pairs into the interner as finished entries.
+    ///
+    /// The positions must point to values that have already been written to
+    /// the current output, for example by writing the same dictionary at the
+    /// start of every archive. Imported entries replace existing entries for
+    /// the same values.
+    pub fn import<I>(&mut self, entries: I)
+    where
+        I: IntoIterator<Item = (T, usize)>,
+        T: Hash + Eq,
+        S: BuildHasher,
+    {
+        for (value, pos) in entries {
+            let entry = Entry {
+                pos: Some(pos),
+                seq: self.next_seq,
+                size: 0,
+                refs: 0,
+            };
+            if let Some(replaced) = self.value_to_entry.insert(value, entry) {
+                self.retained -= replaced.size;
+            }
+            self.next_seq += 1;
+        }
+    }
+}
+
+impl<T, S: Default> Default for Interner<T, S> {
     fn default() -> Self {
-        Self::new()
+        Self::with_hasher(S::default())
     }
 }
 
 #[derive(Debug)]
-struct NotStarted;
+struct BudgetExceeded {
+    size: usize,
+    retained: usize,
+    max_bytes: usize,
+}
 
-impl fmt::Display for NotStarted {
+impl fmt::Display for BudgetExceeded {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "value was not started interning")
+        write!(
+            f,
+            "interning a value of {} bytes would exceed the interner budget \
+             of {} bytes ({} bytes already retained)",
+            self.size, self.max_bytes, self.retained,
+        )
     }
 }
 
-impl Error for NotStarted {}
+impl Error for BudgetExceeded {}
 
-#[derive(Debug)]
-struct AlreadyFinished;
+impl<T, S, E> Interning<T, E> for Interner<T::Owned, S>
+where
+    T::Owned: Hash + Eq + Borrow<T>,
+    T: Hash + Eq + ToOwned + ?Sized,
+    S: BuildHasher,
+    E: Source,
+{
+    fn start_interning(&mut self, value: &T) -> SharingState {
+        let hash = self.value_to_entry.hasher().hash_one(value);
+        match self
+            .value_to_entry
+            .raw_entry_mut()
+            .from_key_hashed_nocheck(hash, value)
+        {
+            RawEntryMut::Vacant(vacant) => {
+                let value = value.to_owned();
+                let (size, within_budget) = match &self.budget {
+                    Some(budget) => {
+                        let size = (budget.size_of)(&value);
+                        (size, !budget.exceeded_by(self.retained, size))
+                    }
+                    None => (0, true),
+                };
+                if within_budget {
+                    let entry = Entry {
+                        pos: None,
+                        seq: self.next_seq,
+                        size,
+                        refs: 1,
+                    };
+                    vacant.insert_hashed_nocheck(hash, value, entry);
+                    self.next_seq += 1;
+                    self.retained += size;
+                }
+                self.started.push(hash);
+                SharingState::Started
+            }
+            RawEntryMut::Occupied(mut occupied) => {
+                let entry = occupied.get_mut();
+                entry.refs += 1;
+                match entry.pos {
+                    None => SharingState::Pending,
+                    Some(pos) => SharingState::Finished(pos),
+                }
+            }
+        }
+    }
 
-impl fmt::Display for AlreadyFinished {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "value was already finished interning")
+    fn finish_interning(&mut self, value: &T, pos: usize) -> Result<(), E> {
+        let entry = match self.started.pop() {
+            // Values are finished in the reverse order they were started, so
+            // the last hash started usually belongs to this value.
+            Some(hash) => match self
+                .value_to_entry
+                .raw_entry_mut()
+                .from_key_hashed_nocheck(hash, value)
+            {
+                RawEntryMut::Occupied(occupied) => Some(occupied.into_mut()),
+                RawEntryMut::Vacant(_) => {
+                    // Either the value is over budget, or it was started out
+                    // of order and the remaining hashes can't be trusted.
+                    let entry = self.value_to_entry.get_mut(value);
+                    if entry.is_some() {
+                        self.started.clear();
+                    }
+                    entry
+                }
+            },
+            None => self.value_to_entry.get_mut(value),
+        };
+        match entry {
+            None => {
+                // Values over budget were never added, so measure them again
+                // to tell them apart from values which were never started.
+                let budget = match &self.budget {
+                    Some(budget) => budget,
+                    None => fail!(NotStarted),
+                };
+                let size = (budget.size_of)(&value.to_owned());
+                if !budget.exceeded_by(self.retained, size) {
+                    fail!(NotStarted);
+                }
+                match budget.policy {
+                    BudgetPolicy::Fallback => Ok(()),
+                    BudgetPolicy::Error => fail!(BudgetExceeded {
+                        size,
+                        retained: self.retained,
+                        max_bytes: budget.max_bytes,
+                    }),
+                }
+            }
+            Some(Entry {
+                pos: Some(existing),
+                ..
+            }) => match self.duplicates {
+                DuplicatePolicy::Error => fail!(AlreadyFinished {
+                    value: self
+                        .debug_values
+                        .map(|describe| describe(&value.to_owned())),
+                    existing: *existing,
+                    new: pos,
+                }),
+                DuplicatePolicy::KeepFirst => Ok(()),
+                DuplicatePolicy::Overwrite => {
+                    *existing = pos;
+                    Ok(())
+                }
+            },
+            Some(entry) => {
+                entry.pos = Some(pos);
+                Ok(())
+            }
+        }
+    }
+
+    fn contains(&self, value: &T) -> bool {
+        self.value_to_entry.contains_key(value)
+    }
+
+    fn len(&self) -> usize {
+        self.value_to_entry.len()
     }
 }
 
-impl Error for AlreadyFinished {}
+struct LruEntry {
+    pos: Option<usize>,
+    last_used: u64,
+    size: usize,
+}
+
+struct ByteLimit<T> {
+    max_bytes: usize,
+    size_of: fn(&T) -> usize,
+}
+
+/// A value interner which retains a bounded number of values.
+///
+/// Once `capacity` values are retained, starting a new value evicts the
+/// least-recently used finished value. Evicted values are serialized again the
+/// next time they are encountered. Eviction scans the whole table, so this is
+/// intended for moderate capacities.
+pub struct LruInterner<T, S = DefaultHashBuilder> {
+    value_to_entry: HashMap<T, LruEntry, S>,
+    capacity: usize,
+    byte_limit: Option<ByteLimit<T>>,
+    retained: usize,
+    clock: u64,
+}
+
+impl<T> LruInterner<T> {
+    /// Returns a new, empty interner which retains at most `capacity` values.
+    ///
+    /// A `capacity` of zero is treated as one.
+    pub fn new(capacity: usize) -> Self {
+        Self::with_hasher(capacity, DefaultHashBuilder::default())
+    }
+}
 
-impl<T, E> Interning<T, E> for Interner<T::Owned>
+impl<T, S> LruInterner<T, S> {
+    /// Returns a new, empty interner which retains at most `capacity` values
+    /// and uses the given hash builder to hash them.
+    ///
+    /// A `capacity` of zero is treated as one.
+    pub fn with_hasher(capacity: usize, hash_builder: S) -> Self {
+        Self {
+            value_to_entry: HashMap::with_hasher(hash_builder),
+            capacity: capacity.max(1),
+            byte_limit: None,
+            retained: 0,
+            clock: 0,
+        }
+    }
+
+    /// Limits the total size of the values retained by the interner.
+    ///
+    /// Starting a new value evicts least-recently used finished values until
+    /// it fits within `max_bytes`. Values which are larger than `max_bytes` on
+    /// their own are never interned. Sizes are measured with [`ByteSize`],
+    /// which is the byte length for strings and byte slices.
+    pub fn set_max_bytes(&mut self, max_bytes: usize)
+    where
+        T: ByteSize,
+    {
+        self.byte_limit = Some(ByteLimit {
+            max_bytes,
+            size_of: T::byte_size,
+        });
+    }
+
+    /// Returns the total size of the values retained by the interner.
+    ///
+    /// Sizes are only measured while a byte limit is set.
+    pub fn retained_bytes(&self) -> usize {
+        self.retained
+    }
+
+    /// Returns the number of values in the interner.
+    pub fn len(&self) -> usize {
+        self.value_to_entry.len()
+    }
+
+    /// Returns whether the interner is empty.
+    pub fn is_empty(&self) -> bool {
+        self.value_to_entry.is_empty()
+    }
+
+    /// Returns an estimate of the memory retained by the interner in bytes.
+    ///
+    /// This includes the table allocation and the bytes of each value, but
+    /// not any spare capacity held by the values themselves.
+    pub fn memory_usage(&self) -> usize
+    where
+        T: AsRef<[u8]>,
+    {
+        let table = self.value_to_entry.capacity() * size_of::<(T, LruEntry)>();
+        let values = self
+            .value_to_entry
+            .keys()
+            .map(|value| value.as_ref().len())
+            .sum::<usize>();
+        table + values
+    }
+
+    /// Removes all values from the interner while keeping its allocated
+    /// capacity.
+    pub fn clear(&mut self) {
+        self.value_to_entry.clear();
+        self.retained = 0;
+        self.clock = 0;
+    }
+
+    fn is_full(&self, size: usize) -> bool {
+        self.value_to_entry.len() >= self.capacity
+            || self
+                .byte_limit
+                .as_ref()
+                .is_some_and(|limit| self.retained + size > limit.max_bytes)
+    }
+
+    fn evict(&mut self) -> bool {
+        // Pending values can't be evicted because they still need to be
+        // finished.
+        let oldest = self
+            .value_to_entry
+            .values()
+            .filter(|entry| entry.pos.is_some())
+            .map(|entry| entry.last_used)
+            .min();
+        match oldest {
+            Some(oldest) => {
+                let retained = &mut self.retained;
+                self.value_to_entry.retain(|_, entry| {
+                    if entry.last_used == oldest {
+                        *retained -= entry.size;
+                        false
+                    } else {
+                        true
+                    }
+                });
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<T, S, E> Interning<T, E> for LruInterner<T::Owned, S>
 where
     T::Owned: Hash + Eq + Borrow<T>,
     T: Hash + Eq + ToOwned + ?Sized,
+    S: BuildHasher,
     E: Source,
 {
     fn start_interning(&mut self, value: &T) -> SharingState {
-        match self.value_to_pos.get(value) {
-            None => {
-                self.value_to_pos.insert(value.to_owned(), None);
-                SharingState::Started
+        self.clock += 1;
+        let hash = self.value_to_entry.hasher().hash_one(value);
+        if let RawEntryMut::Occupied(mut occupied) = self
+            .value_to_entry
+            .raw_entry_mut()
+            .from_key_hashed_nocheck(hash, value)
+        {
+            let existing = occupied.get_mut();
+            existing.last_used = self.clock;
+            return match existing.pos {
+                None => SharingState::Pending,
+                Some(pos) => SharingState::Finished(pos),
+            };
+        }
+
+        let owned = value.to_owned();
+        let size = match &self.byte_limit {
+            Some(limit) => {
+                let size = (limit.size_of)(&owned);
+                if size > limit.max_bytes {
+                    return SharingState::Started;
+                }
+                size
             }
-            Some(None) => SharingState::Pending,
-            Some(Some(pos)) => SharingState::Finished(*pos),
+            None => 0,
+        };
+
+        // Evicting invalidates the vacant entry, so look up the free slot again
+        // with the hash from before. Values are retained even if pending values
+        // keep the interner full.
+        while self.is_full(size) && self.evict() {}
+        if let RawEntryMut::Vacant(vacant) = self
+            .value_to_entry
+            .raw_entry_mut()
+            .from_hash(hash, |_| false)
+        {
+            let entry = LruEntry {
+                pos: None,
+                last_used: self.clock,
+                size,
+            };
+            vacant.insert_hashed_nocheck(hash, owned, entry);
+            self.retained += size;
         }
+        SharingState::Started
     }
 
     fn finish_interning(&mut self, value: &T, pos: usize) -> Result<(), E> {
-        match self.value_to_pos.get_mut(value) {
-            None => fail!(NotStarted),
-            Some(Some(_)) => fail!(AlreadyFinished),
-            Some(x) => {
-                *x = Some(pos);
+        match self.value_to_entry.get_mut(value) {
+            None => match &self.byte_limit {
+                Some(limit)
+                    if (limit.size_of)(&value.to_owned()) > limit.max_bytes =>
+                {
+                    Ok(())
+                }
+                _ => fail!(NotStarted),
+            },
+            Some(LruEntry {
+                pos: Some(existing),
+                ..
+            }) => fail!(AlreadyFinished {
+                value: None,
+                existing: *existing,
+                new: pos,
+            }),
+            Some(entry) => {
+                entry.pos = Some(pos);
                 Ok(())
             }
         }
     }
+
+    fn contains(&self, value: &T) -> bool {
+        self.value_to_entry.contains_key(value)
+    }
+
+    fn len(&self) -> usize {
+        self.value_to_entry.len()
+    }
 }
@@ -29,32 +29,70 @@
     13.512-13.512-2.702 2.703-2.702-8.107-8.107z"/%3E%3C/svg%3E
 "#)]
 #![cfg_attr(miri, feature(alloc_layout_extra))]
-#![cfg(feature = "alloc")]
+#[cfg(feature = "alloc")]
 extern crate alloc;
 
 #[cfg(feature = "alloc")]
+mod api;
+mod array;
+#[cfg(feature = "alloc")]
+mod btree;
+#[cfg(feature = "alloc")]
+mod each;
+mod filter;
+#[cfg(feature = "indexmap")]
+mod index;
+#[cfg(feature = "hashbrown")]
 mod interner;
+#[cfg(feature = "alloc")]
+mod normalize;
+mod observe;
 mod polyfill;
+mod size;
+mod stats;
+#[cfg(feature = "alloc")]
+mod symbol;
 
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+use alloc::sync::Arc;
 #[cfg(feature = "alloc")]
-use alloc::boxed::Box;
+use alloc::{boxed::Box, rc::Rc};
 use core::{
     alloc::Layout, borrow::Borrow, error::Error, fmt, marker::PhantomData,
     ops::Deref, ptr::NonNull,
 };
 
+#[cfg(feature = "alloc")]
+use rkyv::{
+    de::{ErasedPtr, Pooling, PoolingState},
+    rancor::ResultExt as _,
+    traits::LayoutRaw,
+    DeserializeUnsized,
+};
 use rkyv::{
-    rancor::{fail, Fallible, ResultExt as _, Source, Strategy},
+    primitive::FixedIsize,
+    rancor::{fail, Fallible, Source, Strategy},
     rc::{ArchivedRc, Flavor, RcResolver},
     ser::{sharing::SharingState, Allocator, Positional, Sharing, Writer},
-    traits::LayoutRaw,
     with::{ArchiveWith, DeserializeWith, SerializeWith},
-    Archive, ArchiveUnsized, Deserialize, DeserializeUnsized, Place, Serialize,
-    SerializeUnsized,
+    Archive, ArchiveUnsized, Deserialize, Place, Serialize, SerializeUnsized,
 };
 
 #[cfg(feature = "alloc")]
+pub use self::api::*;
+#[cfg(feature = "alloc")]
+pub use self::btree::*;
+#[cfg(feature = "alloc")]
+pub use self::each::*;
+#[cfg(feature = "indexmap")]
+pub use self::index::*;
+#[cfg(feature = "hashbrown")]
 pub use self::interner::*;
+#[cfg(feature = "alloc")]
+pub use self::normalize::*;
+#[cfg(feature = "alloc")]
+pub use self::symbol::*;
+pub use self::{array::*, filter::*, observe::*, size::*, stats::*};
 
 /// A shared value interning strategy.
 ///
@@ -67,6 +105,26 @@ pub trait Interning<T: ?Sized, E = <Self as Fallible>::Error> {
     ///
     /// Returns an error if the value was not pending.
     fn finish_interning(&mut self, value: &T, pos: usize) -> Result<(), E>;
+
+    /// Returns whether the given value is pending or finished interning.
+    ///
+    /// By default, this returns `false`.
+    fn contains(&self, value: &T) -> bool {
+        let _ = value;
+        false
+    }
+
+    /// Returns the number of values which are pending or finished interning.
+    ///
+    /// By default, this returns `0`.
+    fn len(&self) -> usize {
+        0
+    }
+
+    /// Returns whether no values are pending or finished interning.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 #[derive(Debug)]
@@ -80,6 +138,99 @@ impl fmt::Display for CyclicInternedValueError {
 
 impl Error for CyclicInternedValueError {}
 
+#[derive(Debug)]
+struct NotStarted;
+
+impl fmt::Display for NotStarted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "value was not started interning")
+    }
+}
+
+impl Error for NotStarted {}
+
+/// A description of a value included in errors about it.
+#[cfg(feature = "alloc")]
+type ValueDescription = alloc::string::String;
+#[cfg(not(feature = "alloc"))]
+type ValueDescription = core::convert::Infallible;
+
+#[derive(Debug)]
+struct AlreadyFinished {
+    value: Option<ValueDescription>,
+    existing: usize,
+    new: usize,
+}
+
+impl fmt::Display for AlreadyFinished {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.value {
+            Some(value) => write!(
+                f,
+                "value {} was already finished interning at position {}, but \
+                 was finished again at position {}",
+                value, self.existing, self.new,
+            ),
+            None => write!(
+                f,
+                "value was already finished interning at position {}, but was \
+                 finished again at position {}",
+                self.existing, self.new,
+            ),
+        }
+    }
+}
+
+impl Error for AlreadyFinished {}
+
+#[derive(Debug)]
+struct OffsetOutOfRange {
+    pos: usize,
+    end: usize,
+    limit: usize,
+}
+
+impl fmt::Display for OffsetOutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "interned value at position {} can't be referenced from position \
+             {}, since relative offsets are limited to {} bytes",
+            self.pos, self.end, self.limit,
+        )
+    }
+}
+
+impl Error for OffsetOutOfRange {}
+
+/// The largest offset which can be stored in a relative pointer with the
+/// configured pointer width.
+const MAX_OFFSET: usize = if FixedIsize::MAX as u64 > usize::MAX as u64 {
+    usize::MAX
+} else {
+    FixedIsize::MAX as usize
+};
+
+/// Returns a resolver for an interned value at `pos`.
+///
+/// The value is referenced from a position at or after the end of the
+/// output, so this fails if the value is already out of range from there.
+fn resolver_at<S>(serializer: &S, pos: usize) -> Result<RcResolver, S::Error>
+where
+    S: Positional + Fallible + ?Sized,
+    S::Error: Source,
+{
+    let end = serializer.pos();
+    if pos > MAX_OFFSET || end.saturating_sub(pos) > MAX_OFFSET {
+        fail!(OffsetOutOfRange {
+            pos,
+            end,
+            limit: MAX_OFFSET,
+        });
+    }
+    Ok(RcResolver::from_pos(pos))
+}
+
 /// Helper methods for [`Interning`].
 pub trait InterningExt<T: ?Sized, E>: Interning<T, E> {
     /// Interns and serializes a value.
@@ -104,6 +255,27 @@ pub trait InterningExt<T: ?Sized, E>: Interning<T, E> {
             SharingState::Finished(pos) => Ok(pos),
         }
     }
+
+    /// Interns and serializes each value of a dictionary.
+    ///
+    /// This is typically called before serializing the root object so that
+    /// frequently-used values are written once up front and every later
+    /// occurrence resolves to them.
+    fn serialize_dictionary<'a, I>(
+        &mut self,
+        values: I,
+    ) -> Result<(), <Self as Fallible>::Error>
+    where
+        Self: Fallible<Error = E>,
+        E: Source,
+        I: IntoIterator<Item = &'a T>,
+        T: SerializeUnsized<Self> + 'a,
+    {
+        for value in values {
+            self.serialize_interned(value)?;
+        }
+        Ok(())
+    }
 }
 
 impl<S, T, E> InterningExt<T, E> for S
@@ -123,8 +295,8 @@ impl Flavor for InternFlavor {
 /// A wrapper that pools copies of the same value to reduce serialized size.
 ///
 /// # Example
-///
-/// ```
+#[cfg_attr(feature = "alloc", doc = "```")]
+#[cfg_attr(not(feature = "alloc"), doc = "```ignore")]
 /// use rkyv::Archive;
 /// use rkyv_intern::Intern;
 ///
@@ -160,7 +332,8 @@ where
         field: &T,
         serializer: &mut S,
     ) -> Result<Self::Resolver, <S as Fallible>::Error> {
-        Ok(RcResolver::from_pos(serializer.serialize_interned(field)?))
+        let pos = serializer.serialize_interned(field)?;
+        resolver_at(serializer, pos)
     }
 }
 
@@ -225,7 +398,8 @@ where
         field: &T,
         serializer: &mut S,
     ) -> Result<Self::Resolver, <S as Fallible>::Error> {
-        Ok(RcResolver::from_pos(serializer.serialize_interned(field)?))
+        let pos = serializer.serialize_interned(field)?;
+        resolver_at(serializer, pos)
     }
 }
 
@@ -316,9 +490,8 @@ where
         field: &T,
         serializer: &mut S,
     ) -> Result<Self::Resolver, <S as Fallible>::Error> {
-        Ok(RcResolver::from_pos(
-            serializer.serialize_interned(field.borrow())?,
-        ))
+        let pos = serializer.serialize_interned(field.borrow())?;
+        resolver_at(serializer, pos)
     }
 }
 
@@ -354,6 +527,265 @@ where
     }
 }
 
+/// A wrapper that shares copies of the same value both when serializing and
+/// when deserializing.
+///
+/// This archives shared pointers like [`DerefIntern`]. When deserializing
+/// `Arc` or `Rc` fields with a deserializer that supports pooling, every field
+/// that refers to the same archived value gets a clone of one shared pointer
+/// instead of a fresh allocation. `Arc` and `Rc` fields which refer to the
+/// same archived value each get their own allocation, and empty values are
+/// never pooled since they don't allocate. With the `internment`
+/// and `ustr` features, `internment::Intern` and `ustr::Ustr` fields are
+/// resolved through their global interners.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use rkyv::Archive;
+/// use rkyv_intern::SharedIntern;
+///
+/// #[derive(Archive)]
+/// struct Example {
+///     #[rkyv(with = SharedIntern)]
+///     name: Arc<str>,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct SharedIntern;
+
+impl<T: Deref> ArchiveWith<T> for SharedIntern
+where
+    T::Target: ArchiveUnsized,
+{
+    type Archived =
+        ArchivedRc<<T::Target as ArchiveUnsized>::Archived, InternFlavor>;
+    type Resolver = RcResolver;
+
+    fn resolve_with(
+        field: &T,
+        resolver: Self::Resolver,
+        out: Place<Self::Archived>,
+    ) {
+        ArchivedRc::resolve_from_ref(field.deref(), resolver, out);
+    }
+}
+
+impl<T, S> SerializeWith<T, S> for SharedIntern
+where
+    T: Deref,
+    T::Target: SerializeUnsized<S>,
+    S: Interning<T::Target> + Writer + Fallible + ?Sized,
+    S::Error: Source,
+{
+    fn serialize_with(
+        field: &T,
+        serializer: &mut S,
+    ) -> Result<Self::Resolver, <S as Fallible>::Error> {
+        let pos = serializer.serialize_interned(field)?;
+        resolver_at(serializer, pos)
+    }
+}
+
+/// The pooled pointers to a value deserialized through [`SharedIntern`].
+///
+/// `Arc` and `Rc` fields can refer to the same archived value, so the pool
+/// holds a single slot for each value with one pointer of each type.
+#[cfg(feature = "alloc")]
+struct SharedSlot<T: ?Sized> {
+    #[cfg(target_has_atomic = "ptr")]
+    arc: Option<Arc<T>>,
+    rc: Option<Rc<T>>,
+}
+
+#[cfg(feature = "alloc")]
+impl SharedIntern {
+    /// Returns the pooled slot for the given value, or `None` if the value
+    /// can't be pooled.
+    ///
+    /// The pool is keyed by archived address. An empty value takes up no
+    /// bytes, so it can share its address with the next value written, which
+    /// may be of another type or pooled by rkyv itself. Empty values are never
+    /// pooled, and each one gets its own (zero-sized) allocation.
+    fn pooled_slot<T, D>(
+        value: &T::Archived,
+        deserializer: &mut D,
+    ) -> Result<Option<NonNull<SharedSlot<T>>>, D::Error>
+    where
+        T: ArchiveUnsized + ?Sized,
+        D: Pooling + Fallible + ?Sized,
+        D::Error: Source,
+    {
+        unsafe fn drop_slot<T: ?Sized>(ptr: ErasedPtr) {
+            unsafe {
+                drop(Box::from_raw(ptr.downcast_unchecked::<SharedSlot<T>>()));
+            }
+        }
+
+        if core::mem::size_of_val(value) == 0 {
+            return Ok(None);
+        }
+
+        let address = value as *const T::Archived as *const () as usize;
+        let slot = match deserializer.start_pooling(address) {
+            PoolingState::Started => {
+                let slot = Box::into_raw(Box::new(SharedSlot::<T> {
+                    #[cfg(target_has_atomic = "ptr")]
+                    arc: None,
+                    rc: None,
+                }));
+                let result = unsafe {
+                    deserializer.finish_pooling(
+                        address,
+                        ErasedPtr::new(slot),
+                        drop_slot::<T>,
+                    )
+                };
+                match result {
+                    Ok(()) => unsafe { NonNull::new_unchecked(slot) },
+                    Err(error) => {
+                        drop(unsafe { Box::from_raw(slot) });
+                        return Err(error);
+                    }
+                }
+            }
+            PoolingState::Pending => fail!(CyclicInternedValueError),
+            PoolingState::Finished(ptr) => unsafe {
+                NonNull::new_unchecked(
+                    ptr.downcast_unchecked::<SharedSlot<T>>(),
+                )
+            },
+        };
+        Ok(Some(slot))
+    }
+
+    fn deserialize_boxed<T, D>(
+        value: &T::Archived,
+        deserializer: &mut D,
+    ) -> Result<Box<T>, D::Error>
+    where
+        T: ArchiveUnsized + LayoutRaw + ?Sized,
+        T::Archived: DeserializeUnsized<T, D>,
+        D: Fallible + ?Sized,
+        D::Error: Source,
+    {
+        let metadata = value.deserialize_metadata();
+        let layout = T::layout_raw(metadata).into_error()?;
+        let data_address = if layout.size() > 0 {
+            unsafe { ::alloc::alloc::alloc(layout) }
+        } else {
+            polyfill::dangling(&layout).as_ptr()
+        };
+
+        let out =
+            rkyv::ptr_meta::from_raw_parts_mut(data_address.cast(), metadata);
+
+        unsafe {
+            value.deserialize_unsized(deserializer, out)?;
+            Ok(Box::from_raw(out))
+        }
+    }
+}
+
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+impl<T, D> DeserializeWith<ArchivedRc<T::Archived, InternFlavor>, Arc<T>, D>
+    for SharedIntern
+where
+    T: ArchiveUnsized + LayoutRaw + ?Sized,
+    T::Archived: DeserializeUnsized<T, D>,
+    D: Pooling + Fallible + ?Sized,
+    D::Error: Source,
+{
+    fn deserialize_with(
+        field: &ArchivedRc<T::Archived, InternFlavor>,
+        deserializer: &mut D,
+    ) -> Result<Arc<T>, <D as Fallible>::Error> {
+        let slot = Self::pooled_slot::<T, D>(field.get(), deserializer)?;
+        if let Some(slot) = slot {
+            if let Some(arc) = unsafe { &slot.as_ref().arc } {
+                return Ok(arc.clone());
+            }
+        }
+
+        let arc: Arc<T> =
+            Self::deserialize_boxed::<T, D>(field.get(), deserializer)?.into();
+        if let Some(mut slot) = slot {
+            unsafe {
+                slot.as_mut().arc = Some(arc.clone());
+            }
+        }
+        Ok(arc)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T, D> DeserializeWith<ArchivedRc<T::Archived, InternFlavor>, Rc<T>, D>
+    for SharedIntern
+where
+    T: ArchiveUnsized + LayoutRaw + ?Sized,
+    T::Archived: DeserializeUnsized<T, D>,
+    D: Pooling + Fallible + ?Sized,
+    D::Error: Source,
+{
+    fn deserialize_with(
+        field: &ArchivedRc<T::Archived, InternFlavor>,
+        deserializer: &mut D,
+    ) -> Result<Rc<T>, <D as Fallible>::Error> {
+        let slot = Self::pooled_slot::<T, D>(field.get(), deserializer)?;
+        if let Some(slot) = slot {
+            if let Some(rc) = unsafe { &slot.as_ref().rc } {
+                return Ok(rc.clone());
+            }
+        }
+
+        let rc: Rc<T> =
+            Self::deserialize_boxed::<T, D>(field.get(), deserializer)?.into();
+        if let Some(mut slot) = slot {
+            unsafe {
+                slot.as_mut().rc = Some(rc.clone());
+            }
+        }
+        Ok(rc)
+    }
+}
+
+#[cfg(feature = "internment")]
+impl<T, D>
+    DeserializeWith<
+        ArchivedRc<T::Archived, InternFlavor>,
+        internment::Intern<T>,
+        D,
+    > for SharedIntern
+where
+    T: Archive + Eq + core::hash::Hash + Send + Sync + 'static,
+    T::Archived: Deserialize<T, D>,
+    D: Fallible + ?Sized,
+{
+    fn deserialize_with(
+        field: &ArchivedRc<T::Archived, InternFlavor>,
+        deserializer: &mut D,
+    ) -> Result<internment::Intern<T>, <D as Fallible>::Error> {
+        let value = field.get().deserialize(deserializer)?;
+        Ok(internment::Intern::new(value))
+    }
+}
+
+#[cfg(feature = "ustr")]
+impl<D> DeserializeWith<ArchivedRc<str, InternFlavor>, ustr::Ustr, D>
+    for SharedIntern
+where
+    D: Fallible + ?Sized,
+{
+    fn deserialize_with(
+        field: &ArchivedRc<str, InternFlavor>,
+        _: &mut D,
+    ) -> Result<ustr::Ustr, <D as Fallible>::Error> {
+        Ok(ustr::Ustr::from(field.get()))
+    }
+}
+
 /// A basic adapter that can add interning capabilities to a serializer.
 ///
 /// While this struct is useful for ergonomics, it's best to define a custom
@@ -435,6 +867,14 @@ where
     fn finish_interning(&mut self, value: &T, pos: usize) -> Result<(), E> {
         self.interning.finish_interning(value, pos)
     }
+
+    fn contains(&self, value: &T) -> bool {
+        self.interning.contains(value)
+    }
+
+    fn len(&self) -> usize {
+        self.interning.len()
+    }
 }
 
 impl<S, T, E> Interning<T, E> for Strategy<S, E>
@@ -449,26 +889,90 @@ where
     fn finish_interning(&mut self, value: &T, pos: usize) -> Result<(), E> {
         S::finish_interning(self, value, pos)
     }
+
+    fn contains(&self, value: &T) -> bool {
+        S::contains(self, value)
+    }
+
+    fn len(&self) -> usize {
+        S::len(self)
+    }
+}
+
+impl<I, T, E> Interning<T, E> for &mut I
+where
+    I: Interning<T, E> + ?Sized,
+    T: ?Sized,
+{
+    fn start_interning(&mut self, value: &T) -> SharingState {
+        I::start_interning(self, value)
+    }
+
+    fn finish_interning(&mut self, value: &T, pos: usize) -> Result<(), E> {
+        I::finish_interning(self, value, pos)
+    }
+
+    fn contains(&self, value: &T) -> bool {
+        I::contains(self, value)
+    }
+
+    fn len(&self) -> usize {
+        I::len(self)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<I, T, E> Interning<T, E> for Box<I>
+where
+    I: Interning<T, E> + ?Sized,
+    T: ?Sized,
+{
+    fn start_interning(&mut self, value: &T) -> SharingState {
+        I::start_interning(self, value)
+    }
+
+    fn finish_interning(&mut self, value: &T, pos: usize) -> Result<(), E> {
+        I::finish_interning(self, value, pos)
+    }
+
+    fn contains(&self, value: &T) -> bool {
+        I::contains(self, value)
+    }
+
+    fn len(&self) -> usize {
+        I::len(self)
+    }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "hashbrown"))]
 mod tests {
+    use core::fmt::Debug;
+
     use ::alloc::{
+        boxed::Box,
         string::{String, ToString},
         vec::Vec,
     };
     use rkyv::{
         access_unchecked,
-        api::serialize_using,
+        api::{deserialize_using, serialize_using},
+        de::Pool,
         deserialize,
-        rancor::{Panic, ResultExt, Strategy},
-        ser::{allocator::ArenaHandle, Serializer},
+        rancor::{Error, Panic, ResultExt, Strategy},
+        ser::{
+            allocator::ArenaHandle, sharing::SharingState, Positional,
+            Serializer, Writer,
+        },
         util::{with_arena, AlignedVec},
-        Archive, Archived, Deserialize, Serialize,
+        Archive, Archived, Deserialize, Portable, Serialize,
     };
 
     use crate::{
-        BorrowIntern, DerefIntern, Intern, Interner, InterningAdapter,
+        to_bytes, to_bytes_with, ArrayInterner, BTreeInterner, BorrowIntern,
+        BudgetPolicy, DerefIntern, DuplicatePolicy, Intern, InternObserver,
+        Interner, Interning, InterningAdapter, InterningExt, MaxEntries,
+        MinSize, Normalize, Observe, SharedIntern, StatsInterning,
+        StatsPooling, SymbolInterner,
     };
 
     const USERS: [&str; 4] = [
@@ -502,6 +1006,47 @@ mod tests {
         })
     }
 
+    #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+    struct Log {
+        #[rkyv(with = BorrowIntern<str>)]
+        user: String,
+        code: u16,
+    }
+
+    #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+    struct OwnedLog {
+        #[rkyv(with = Intern)]
+        user: String,
+        code: u16,
+    }
+
+    fn logs() -> Vec<Log> {
+        (0..1000)
+            .map(|i| Log {
+                user: USERS[i % USERS.len()].to_string(),
+                code: (i % u16::MAX as usize) as u16,
+            })
+            .collect()
+    }
+
+    fn owned_logs() -> Vec<OwnedLog> {
+        logs()
+            .into_iter()
+            .map(|Log { user, code }| OwnedLog { user, code })
+            .collect()
+    }
+
+    fn assert_round_trip<T>(bytes: &[u8], value: &T) -> T
+    where
+        T: Archive + Debug + PartialEq,
+        T::Archived: Portable + Deserialize<T, Strategy<Pool, Panic>>,
+    {
+        let archived = unsafe { access_unchecked::<T::Archived>(bytes) };
+        let deserialized = deserialize::<T, Panic>(archived).always_ok();
+        assert_eq!(&deserialized, value);
+        deserialized
+    }
+
     #[test]
     fn intern_strings() {
         #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
@@ -597,4 +1142,1274 @@ mod tests {
         let deserialized = deserialize::<Vec<Log>, Panic>(archived).always_ok();
         assert_eq!(deserialized, value);
     }
+
+    #[test]
+    fn intern_each_collection() {
+        use ::alloc::collections::{BTreeSet, LinkedList, VecDeque};
+        use hashbrown::HashSet;
+
+        use crate::InternEach;
+
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Tags {
+            #[rkyv(with = InternEach<Intern>)]
+            deque: VecDeque<String>,
+            #[rkyv(with = InternEach<Intern>)]
+            list: LinkedList<String>,
+            #[rkyv(with = InternEach<Intern>)]
+            btree: BTreeSet<String>,
+            #[rkyv(with = InternEach<Intern>)]
+            hash: HashSet<String>,
+        }
+
+        let users = || USERS.iter().map(|user| user.to_string());
+        let value = Tags {
+            deque: users().collect(),
+            list: users().collect(),
+            btree: users().collect(),
+            hash: users().collect(),
+        };
+
+        let bytes = serialize_interned::<_, Panic>(&value).always_ok();
+        let archived = unsafe { access_unchecked::<Archived<Tags>>(&bytes) };
+        for (deque, list) in archived.deque.iter().zip(archived.list.iter()) {
+            assert_eq!(deque.get().as_ptr(), list.get().as_ptr());
+        }
+
+        assert_round_trip(&bytes, &value);
+    }
+
+    #[test]
+    fn intern_each_tuple() {
+        use crate::InternEach;
+
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Edge {
+            #[rkyv(with = InternEach<Intern>)]
+            users: (String, String),
+        }
+
+        let value = (0..USERS.len())
+            .map(|i| Edge {
+                users: (
+                    USERS[i].to_string(),
+                    USERS[(i + 1) % USERS.len()].to_string(),
+                ),
+            })
+            .collect::<Vec<_>>();
+
+        let bytes = serialize_interned::<_, Panic>(&value).always_ok();
+        let archived = unsafe {
+            access_unchecked::<Archived<Vec<Edge>>>(&bytes)
+        };
+        assert_eq!(
+            archived[0].users.1.get().as_ptr(),
+            archived[1].users.0.get().as_ptr(),
+        );
+
+        assert_round_trip(&bytes, &value);
+    }
+
+    #[test]
+    fn btree_intern_strings() {
+        let value = logs();
+
+        let bytes =
+            to_bytes_with::<_, Panic>(&value, BTreeInterner::<String>::new())
+                .always_ok();
+        assert!(bytes.len() < 20_000);
+
+        assert_round_trip(&bytes, &value);
+    }
+
+    #[test]
+    fn array_intern_strings() {
+        let value = owned_logs();
+
+        let bytes = to_bytes_with::<_, Panic>(
+            &value,
+            ArrayInterner::<String, 4>::new(),
+        )
+        .always_ok();
+        assert!(bytes.len() < 20_000);
+
+        assert_round_trip(&bytes, &value);
+
+        let result = to_bytes_with::<_, Error>(
+            &value,
+            ArrayInterner::<String, 2>::new(),
+        );
+        assert!(result.is_err());
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn array_intern_heapless_strings() {
+        #[derive(Archive, Serialize)]
+        struct HeaplessLog {
+            #[rkyv(with = DerefIntern)]
+            user: heapless::String<64>,
+            code: u16,
+        }
+
+        let value = logs()
+            .into_iter()
+            .map(|Log { user, code }| {
+                let mut owned = heapless::String::new();
+                owned.push_str(&user).unwrap();
+                HeaplessLog { user: owned, code }
+            })
+            .collect::<Vec<_>>();
+
+        let bytes = to_bytes_with::<_, Panic>(
+            &value,
+            ArrayInterner::<heapless::String<64>, 4>::new(),
+        )
+        .always_ok();
+        assert!(bytes.len() < 20_000);
+
+        let archived = unsafe {
+            access_unchecked::<Archived<Vec<HeaplessLog>>>(&bytes)
+        };
+        for (a, b) in archived.iter().zip(value.iter()) {
+            assert_eq!(&*a.user, b.user.as_str());
+            assert_eq!(a.code, b.code);
+        }
+
+        // Strings which don't fit in a slot are serialized without interning.
+        let bytes = to_bytes_with::<_, Error>(
+            &value,
+            ArrayInterner::<heapless::String<16>, 4>::new(),
+        )
+        .unwrap();
+        assert!(bytes.len() > 40_000);
+    }
+
+    // Without interning, this archive is too large for 16-bit offsets.
+    #[cfg(not(feature = "pointer_width_16"))]
+    #[test]
+    fn lru_intern_strings() {
+        use crate::LruInterner;
+
+        let value = logs();
+
+        let bytes = to_bytes_with::<_, Panic>(
+            &value,
+            LruInterner::<String>::new(USERS.len()),
+        )
+        .always_ok();
+        assert!(bytes.len() < 20_000);
+
+        // Cycling through more users than the interner can hold evicts every
+        // user before it is seen again.
+        let evicted = to_bytes_with::<_, Panic>(
+            &value,
+            LruInterner::<String>::new(USERS.len() - 1),
+        )
+        .always_ok();
+        assert!(evicted.len() > 20_000);
+
+        assert_round_trip(&evicted, &value);
+    }
+
+    #[test]
+    fn lru_max_bytes() {
+        use crate::LruInterner;
+
+        let mut interner = LruInterner::<String>::new(10);
+        interner.set_max_bytes(8);
+        for (pos, value) in ["aaaa", "bbbb", "cccc"].iter().enumerate() {
+            Interning::<str, Panic>::start_interning(&mut interner, value);
+            Interning::<str, Panic>::finish_interning(
+                &mut interner,
+                value,
+                pos,
+            )
+            .always_ok();
+        }
+        assert!(!Interning::<str, Panic>::contains(&interner, "aaaa"));
+        assert_eq!(interner.len(), 2);
+        assert_eq!(interner.retained_bytes(), 8);
+
+        let large = "d".repeat(9);
+        assert!(matches!(
+            Interning::<str, Panic>::start_interning(&mut interner, &large),
+            SharingState::Started,
+        ));
+        Interning::<str, Panic>::finish_interning(&mut interner, &large, 3)
+            .always_ok();
+        assert!(!Interning::<str, Panic>::contains(&interner, &large));
+        assert_eq!(interner.len(), 2);
+    }
+
+    #[test]
+    fn lru_capacity_zero() {
+        use crate::LruInterner;
+
+        let mut interner = LruInterner::<String>::new(0);
+        for (pos, value) in ["a", "b"].iter().enumerate() {
+            Interning::<str, Panic>::start_interning(&mut interner, value);
+            Interning::<str, Panic>::finish_interning(
+                &mut interner,
+                value,
+                pos,
+            )
+            .always_ok();
+        }
+        assert!(!Interning::<str, Panic>::contains(&interner, "a"));
+        assert!(Interning::<str, Panic>::contains(&interner, "b"));
+    }
+
+    // Without interning, this archive is too large for 16-bit offsets.
+    #[cfg(not(feature = "pointer_width_16"))]
+    #[test]
+    fn min_size_skips_small_values() {
+        let value = logs();
+
+        let bytes = to_bytes_with::<_, Panic>(
+            &value,
+            MinSize::new(Interner::<String>::default(), 16),
+        )
+        .always_ok();
+        assert!(bytes.len() < 20_000);
+
+        let skipped = to_bytes_with::<_, Panic>(
+            &value,
+            MinSize::new(Interner::<String>::default(), 64),
+        )
+        .always_ok();
+        assert!(skipped.len() > 20_000);
+
+        assert_round_trip(&skipped, &value);
+    }
+
+    #[test]
+    fn min_size_measures_owned_values() {
+        let value = owned_logs();
+
+        // Every user is longer than 32 bytes, but a `String` is smaller.
+        let bytes = to_bytes_with::<_, Panic>(
+            &value,
+            MinSize::new(Interner::<String>::default(), 32),
+        )
+        .always_ok();
+        assert!(bytes.len() < 20_000);
+
+        assert_round_trip(&bytes, &value);
+    }
+
+    // Without interning, this archive is too large for 16-bit offsets.
+    #[cfg(not(feature = "pointer_width_16"))]
+    #[test]
+    fn max_size_skips_large_values() {
+        use crate::MaxSize;
+
+        let value = owned_logs();
+
+        let bytes = to_bytes_with::<_, Panic>(
+            &value,
+            MaxSize::new(Interner::<String>::default(), 64),
+        )
+        .always_ok();
+        assert!(bytes.len() < 20_000);
+
+        // Every user is longer than 32 bytes, but a `String` is smaller.
+        let skipped = to_bytes_with::<_, Panic>(
+            &value,
+            MaxSize::new(Interner::<String>::default(), 32),
+        )
+        .always_ok();
+        assert!(skipped.len() > 20_000);
+
+        assert_round_trip(&skipped, &value);
+    }
+
+    // Without interning, this archive is too large for 16-bit offsets.
+    #[cfg(not(feature = "pointer_width_16"))]
+    #[test]
+    fn min_occurrences_skips_rare_values() {
+        use crate::MinOccurrences;
+
+        let value = logs();
+
+        let bytes = to_bytes_with::<_, Panic>(
+            &value,
+            MinOccurrences::new(Interner::<String>::default(), 2),
+        )
+        .always_ok();
+        assert!(bytes.len() < 20_000);
+
+        let skipped = to_bytes_with::<_, Panic>(
+            &value,
+            MinOccurrences::new(Interner::<String>::default(), 1000),
+        )
+        .always_ok();
+        assert!(skipped.len() > 20_000);
+
+        assert_round_trip(&bytes, &value);
+    }
+
+    #[test]
+    fn symbol_intern_strings() {
+        let value = logs();
+
+        let symbol_of =
+            |user: &str| USERS.iter().position(|u| *u == user).unwrap();
+        let bytes =
+            to_bytes_with::<_, Panic>(&value, SymbolInterner::new(symbol_of))
+                .always_ok();
+        assert!(bytes.len() < 20_000);
+
+        assert_round_trip(&bytes, &value);
+    }
+
+    #[test]
+    fn symbol_interner_large_symbols() {
+        use crate::DEFAULT_MAX_SYMBOLS;
+
+        let mut interner = SymbolInterner::new(|value: &str| match value {
+            "max" => usize::MAX,
+            _ => DEFAULT_MAX_SYMBOLS,
+        });
+        for value in ["max", "large"].iter() {
+            assert!(matches!(
+                Interning::<str, Panic>::start_interning(&mut interner, value),
+                SharingState::Started,
+            ));
+            Interning::<str, Panic>::finish_interning(&mut interner, value, 0)
+                .always_ok();
+        }
+        assert!(Interning::<str, Panic>::is_empty(&interner));
+    }
+
+    #[test]
+    fn interning_defaults() {
+        struct Forget;
+
+        impl Interning<str, Panic> for Forget {
+            fn start_interning(&mut self, _: &str) -> SharingState {
+                SharingState::Started
+            }
+
+            fn finish_interning(
+                &mut self,
+                _: &str,
+                _: usize,
+            ) -> Result<(), Panic> {
+                Ok(())
+            }
+        }
+
+        assert!(!Interning::<str, Panic>::contains(&Forget, "value"));
+        assert!(Interning::<str, Panic>::is_empty(&Forget));
+    }
+
+    #[test]
+    fn interner_budget() {
+        let value = logs();
+
+        // Only the first two users fit in the budget.
+        let mut interner = Interner::<String>::new();
+        interner.set_budget(100, BudgetPolicy::Fallback);
+        let bytes = to_bytes_with::<_, Panic>(&value, interner).always_ok();
+        assert!(bytes.len() > 20_000);
+
+        assert_round_trip(&bytes, &value);
+
+        let mut interner = Interner::<String>::new();
+        interner.set_budget(100, BudgetPolicy::Error);
+        let result = to_bytes_with::<_, Error>(&value, interner);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn interner_without_byte_size() {
+        #[derive(
+            Archive, Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash,
+        )]
+        struct Key(u32);
+
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Record {
+            #[rkyv(with = Intern)]
+            key: Key,
+        }
+
+        let value = (0..100)
+            .map(|i| Record { key: Key(i % 4) })
+            .collect::<Vec<_>>();
+
+        let bytes = to_bytes_with::<_, Panic>(&value, Interner::<Key>::new())
+            .always_ok();
+        assert_round_trip(&bytes, &value);
+    }
+
+    #[test]
+    fn interner_budget_measures_owned_values() {
+        fn intern(interner: &mut Interner<String>, value: &str) {
+            let value = value.to_string();
+            Interning::<String, Panic>::start_interning(interner, &value);
+            Interning::<String, Panic>::finish_interning(interner, &value, 0)
+                .always_ok();
+        }
+
+        // Only the first two users fit in the budget.
+        let mut interner = Interner::<String>::new();
+        interner.set_budget(100, BudgetPolicy::Fallback);
+        for user in USERS {
+            intern(&mut interner, user);
+        }
+        assert_eq!(interner.len(), 2);
+        assert_eq!(interner.retained_bytes(), USERS[0].len() + USERS[1].len());
+
+        // Imported entries replace retained ones without counting against
+        // the budget.
+        interner.import([(USERS[0].to_string(), 0)]);
+        assert_eq!(interner.retained_bytes(), USERS[1].len());
+    }
+
+    #[test]
+    fn serialize_dictionary() {
+        let value = logs();
+
+        let (bytes, dictionary_len) = with_arena(|arena| {
+            let mut serializer = InterningAdapter::new(
+                Serializer::new(AlignedVec::<8>::new(), arena.acquire(), ()),
+                Interner::<String>::default(),
+            );
+
+            Strategy::<_, Panic>::wrap(&mut serializer)
+                .serialize_dictionary(USERS.iter().copied())
+                .always_ok();
+            let dictionary_len = serializer.pos();
+
+            serialize_using::<_, Panic>(&value, &mut serializer).always_ok();
+
+            (serializer.into_serializer().into_writer(), dictionary_len)
+        });
+
+        let archived = unsafe {
+            access_unchecked::<Archived<Vec<Log>>>(&bytes)
+        };
+        for log in archived.iter() {
+            let offset =
+                log.user.get().as_ptr() as usize - bytes.as_ptr() as usize;
+            assert!(offset < dictionary_len);
+        }
+
+        assert_round_trip(&bytes, &value);
+    }
+
+    #[cfg(feature = "pointer_width_16")]
+    #[test]
+    fn offset_out_of_range() {
+        let result = with_arena(|arena| {
+            let mut serializer = InterningAdapter::new(
+                Serializer::new(AlignedVec::<8>::new(), arena.acquire(), ()),
+                Interner::<String>::default(),
+            );
+
+            let strategy = Strategy::<_, Error>::wrap(&mut serializer);
+            strategy
+                .serialize_dictionary(USERS.iter().copied())
+                .unwrap();
+            strategy.write(&[0; 40_000]).unwrap();
+
+            serialize_using::<_, Error>(&logs()[0], &mut serializer)
+        });
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("limited to 32767 bytes"), "{}", error);
+    }
+
+    #[test]
+    fn interner_import() {
+        let value = logs();
+
+        let (dictionary, exported) = with_arena(|arena| {
+            let mut serializer = InterningAdapter::new(
+                Serializer::new(AlignedVec::<8>::new(), arena.acquire(), ()),
+                Interner::<String>::default(),
+            );
+
+            Strategy::<_, Panic>::wrap(&mut serializer)
+                .serialize_dictionary(USERS.iter().copied())
+                .always_ok();
+
+            let (serializer, interner) = serializer.into_components();
+            (serializer.into_writer(), interner.export())
+        });
+        assert_eq!(exported.len(), USERS.len());
+
+        // Start the next archive with the same dictionary and import the
+        // positions of its values instead of serializing them again.
+        let bytes = with_arena(|arena| {
+            let mut interner = Interner::<String>::default();
+            interner.import(exported.iter().cloned());
+            let mut serializer = InterningAdapter::new(
+                Serializer::new(AlignedVec::<8>::new(), arena.acquire(), ()),
+                interner,
+            );
+
+            Strategy::<_, Panic>::wrap(&mut serializer)
+                .write(&dictionary)
+                .always_ok();
+            serialize_using::<_, Panic>(&value, &mut serializer).always_ok();
+
+            serializer.into_serializer().into_writer()
+        });
+
+        for (user, pos) in exported.iter() {
+            assert_eq!(&bytes[*pos..*pos + user.len()], user.as_bytes());
+        }
+
+        let archived = unsafe {
+            access_unchecked::<Archived<Vec<Log>>>(&bytes)
+        };
+        for log in archived.iter() {
+            let offset =
+                log.user.get().as_ptr() as usize - bytes.as_ptr() as usize;
+            assert!(offset < dictionary.len());
+        }
+
+        assert_round_trip(&bytes, &value);
+    }
+
+    #[test]
+    fn interning_stats() {
+        let value = owned_logs();
+
+        let stats = with_arena(|arena| {
+            let mut serializer = InterningAdapter::new(
+                Serializer::new(AlignedVec::<8>::new(), arena.acquire(), ()),
+                StatsInterning::new(Interner::<String>::default()),
+            );
+
+            serialize_using::<_, Panic>(&value, &mut serializer).always_ok();
+
+            serializer.into_components().1.stats()
+        });
+
+        assert_eq!(stats.lookups, 1000);
+        assert_eq!(stats.hits, 1000 - USERS.len());
+        assert_eq!(stats.misses(), USERS.len());
+        assert_eq!(stats.distinct, USERS.len());
+        let bytes_saved = value[USERS.len()..]
+            .iter()
+            .map(|log| log.user.len())
+            .sum::<usize>();
+        assert_eq!(stats.bytes_saved, bytes_saved);
+    }
+
+    #[test]
+    fn interner_iter() {
+        let value = logs();
+
+        let (bytes, interner) = with_arena(|arena| {
+            let mut serializer = InterningAdapter::new(
+                Serializer::new(AlignedVec::<8>::new(), arena.acquire(), ()),
+                Interner::<String>::default(),
+            );
+
+            serialize_using::<_, Panic>(&value, &mut serializer).always_ok();
+
+            let (serializer, interner) = serializer.into_components();
+            (serializer.into_writer(), interner)
+        });
+
+        let mut users = interner
+            .iter()
+            .map(|(user, _)| user.as_str())
+            .collect::<Vec<_>>();
+        users.sort();
+        assert_eq!(users, USERS);
+
+        for (user, pos) in interner.iter() {
+            assert_eq!(&bytes[pos..pos + user.len()], user.as_bytes());
+        }
+    }
+
+    #[test]
+    fn lowercase_strings() {
+        #[derive(Archive, Serialize)]
+        struct Request {
+            #[rkyv(with = BorrowIntern<str>)]
+            method: String,
+        }
+
+        let value = ["GET", "get", "Get", "POST"]
+            .iter()
+            .map(|method| Request {
+                method: method.to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let bytes = to_bytes_with::<_, Panic>(
+            &value,
+            Normalize::lowercase(Interner::<String>::default()),
+        )
+        .always_ok();
+
+        let archived = unsafe {
+            access_unchecked::<Archived<Vec<Request>>>(&bytes)
+        };
+        assert_eq!(archived[0].method.get(), "GET");
+        assert_eq!(archived[1].method.get(), "GET");
+        assert_eq!(archived[2].method.get(), "GET");
+        assert_eq!(archived[3].method.get(), "POST");
+        assert_eq!(
+            archived[0].method.get().as_ptr(),
+            archived[2].method.get().as_ptr(),
+        );
+    }
+
+    #[test]
+    fn lowercase_titlecase() {
+        let mut interning = Normalize::lowercase(Interner::<String>::default());
+        Interning::<str, Panic>::start_interning(&mut interning, "\u{1c5}");
+        Interning::<str, Panic>::finish_interning(&mut interning, "\u{1c5}", 0)
+            .always_ok();
+        assert!(Interning::<str, Panic>::contains(&interning, "\u{1c4}"));
+        assert!(Interning::<str, Panic>::contains(&interning, "\u{1c6}"));
+    }
+
+    #[test]
+    fn canonical_paths() {
+        let mut interning = Normalize::path(Interner::<String>::default());
+        Interning::<str, Panic>::start_interning(&mut interning, "a/b");
+        Interning::<str, Panic>::finish_interning(&mut interning, "a/b", 0)
+            .always_ok();
+
+        for path in ["a/b", "a\\b", "a/./b", "a//b/", "./a/b"] {
+            assert!(matches!(
+                Interning::<str, Panic>::start_interning(&mut interning, path),
+                SharingState::Finished(0)
+            ));
+        }
+        for path in ["/a/b", "a/../b", "b/a"] {
+            assert!(!Interning::<str, Panic>::contains(&interning, path));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "unicode-normalization")]
+    fn nfc_strings() {
+        let mut interning = Normalize::nfc(Interner::<String>::default());
+        Interning::<str, Panic>::start_interning(&mut interning, "\u{e9}");
+        Interning::<str, Panic>::finish_interning(&mut interning, "\u{e9}", 0)
+            .always_ok();
+
+        for value in ["\u{e9}", "e\u{301}"] {
+            assert!(matches!(
+                Interning::<str, Panic>::start_interning(&mut interning, value),
+                SharingState::Finished(0)
+            ));
+        }
+        assert!(!Interning::<str, Panic>::contains(&interning, "e"));
+    }
+
+    #[test]
+    fn interner_hashes_values_once() {
+        use core::{cell::Cell, hash::BuildHasher};
+
+        use hashbrown::hash_map::DefaultHashBuilder;
+
+        struct CountHashes<'a> {
+            count: &'a Cell<usize>,
+            inner: DefaultHashBuilder,
+        }
+
+        impl BuildHasher for CountHashes<'_> {
+            type Hasher = <DefaultHashBuilder as BuildHasher>::Hasher;
+
+            fn build_hasher(&self) -> Self::Hasher {
+                self.count.set(self.count.get() + 1);
+                self.inner.build_hasher()
+            }
+        }
+
+        let hashes = Cell::new(0);
+        let mut interner = Interner::<String, _>::with_hasher(CountHashes {
+            count: &hashes,
+            inner: DefaultHashBuilder::default(),
+        });
+        let value = logs();
+        to_bytes_with::<_, Panic>(&value, &mut interner).always_ok();
+
+        // Growing the table rehashes its values, so count the hashes once the
+        // table has room for every value.
+        interner.clear();
+        hashes.set(0);
+        to_bytes_with::<_, Panic>(&value, &mut interner).always_ok();
+        assert_eq!(hashes.get(), value.len());
+    }
+
+    #[test]
+    fn interner_scopes() {
+        fn start(interner: &mut Interner<String>, value: &str) -> SharingState {
+            Interning::<str, Panic>::start_interning(interner, value)
+        }
+
+        fn finish(interner: &mut Interner<String>, value: &str, pos: usize) {
+            Interning::<str, Panic>::finish_interning(interner, value, pos)
+                .always_ok();
+        }
+
+        let mut interner = Interner::new();
+        assert!(matches!(
+            start(&mut interner, "outer"),
+            SharingState::Started
+        ));
+        finish(&mut interner, "outer", 0);
+
+        interner.push_scope();
+        assert!(matches!(
+            start(&mut interner, "inner"),
+            SharingState::Started
+        ));
+        finish(&mut interner, "inner", 8);
+        assert!(matches!(
+            start(&mut interner, "outer"),
+            SharingState::Finished(0)
+        ));
+        assert!(matches!(
+            start(&mut interner, "inner"),
+            SharingState::Finished(8)
+        ));
+        assert!(interner.pop_scope());
+
+        assert!(matches!(
+            start(&mut interner, "outer"),
+            SharingState::Finished(0)
+        ));
+        assert!(matches!(
+            start(&mut interner, "inner"),
+            SharingState::Started
+        ));
+        assert!(!interner.pop_scope());
+    }
+
+    #[test]
+    fn interning_contains_and_len() {
+        let mut interner = Interner::<String>::new();
+        assert!(Interning::<str, Panic>::is_empty(&interner));
+
+        Interning::<str, Panic>::start_interning(&mut interner, "hello");
+        assert!(Interning::<str, Panic>::contains(&interner, "hello"));
+        assert_eq!(Interning::<str, Panic>::len(&interner), 1);
+
+        Interning::<str, Panic>::finish_interning(&mut interner, "hello", 0)
+            .always_ok();
+        assert!(Interning::<str, Panic>::contains(&interner, "hello"));
+        assert!(!Interning::<str, Panic>::contains(&interner, "world"));
+        assert_eq!(interner.len(), 1);
+
+        let mut min_size = MinSize::new(Interner::<String>::new(), 4);
+        Interning::<str, Panic>::start_interning(&mut min_size, "hi");
+        assert!(!Interning::<str, Panic>::contains(&min_size, "hi"));
+        assert!(Interning::<str, Panic>::is_empty(&min_size));
+    }
+
+    #[test]
+    fn interner_memory_usage() {
+        let mut interner = Interner::<String>::new();
+        assert_eq!(interner.memory_usage(), 0);
+
+        let value = "a".repeat(1000);
+        Interning::<str, Panic>::start_interning(&mut interner, &value);
+        assert!(interner.memory_usage() >= 1000);
+
+        interner.clear();
+        assert!(interner.memory_usage() < 1000);
+    }
+
+    #[test]
+    fn min_occurrences_memory_usage() {
+        use crate::MinOccurrences;
+
+        let mut interner = Interner::<String>::new();
+        let mut min_occurrences =
+            MinOccurrences::new(Interner::<String>::new(), 2);
+        for i in 0..100 {
+            let mut value = "a".repeat(100);
+            value.push_str(&i.to_string());
+            Interning::<str, Panic>::start_interning(&mut interner, &value);
+            Interning::<str, Panic>::start_interning(
+                &mut min_occurrences,
+                &value,
+            );
+        }
+
+        assert!(Interning::<str, Panic>::is_empty(&min_occurrences));
+        assert!(min_occurrences.memory_usage() < interner.memory_usage());
+    }
+
+    #[cfg(feature = "indexmap")]
+    #[test]
+    fn index_interner_order() {
+        use crate::IndexInterner;
+
+        let mut interner = IndexInterner::<String>::new();
+        for (i, user) in ["carol", "alice", "carol", "bob"].iter().enumerate() {
+            let state =
+                Interning::<str, Panic>::start_interning(&mut interner, user);
+            if let SharingState::Started = state {
+                Interning::<str, Panic>::finish_interning(
+                    &mut interner,
+                    user,
+                    i * 8,
+                )
+                .always_ok();
+            }
+        }
+
+        let exported = interner.export();
+        assert_eq!(
+            exported,
+            [
+                ("carol".to_string(), 0),
+                ("alice".to_string(), 8),
+                ("bob".to_string(), 24),
+            ]
+        );
+    }
+
+    #[test]
+    fn max_entries_stops_interning() {
+        let mut interning = MaxEntries::new(Interner::<String>::new(), 2);
+        for (i, user) in USERS[..3].iter().enumerate() {
+            let state =
+                Interning::<str, Panic>::start_interning(&mut interning, user);
+            assert!(matches!(state, SharingState::Started));
+            Interning::<str, Panic>::finish_interning(&mut interning, user, i)
+                .always_ok();
+        }
+        assert_eq!(Interning::<str, Panic>::len(&interning), 2);
+
+        assert!(matches!(
+            Interning::<str, Panic>::start_interning(&mut interning, USERS[0]),
+            SharingState::Finished(0)
+        ));
+        assert!(matches!(
+            Interning::<str, Panic>::start_interning(&mut interning, USERS[2]),
+            SharingState::Started
+        ));
+    }
+
+    // Without interning, this archive is too large for 16-bit offsets.
+    #[cfg(not(feature = "pointer_width_16"))]
+    #[test]
+    fn sample_interns_frequent_values() {
+        use crate::Sample;
+
+        let value = logs();
+        let sampled = |rate| {
+            to_bytes_with::<_, Panic>(
+                &value,
+                Sample::new(Interner::<String>::default(), rate, 1),
+            )
+            .always_ok()
+        };
+
+        let bytes = sampled(4);
+        assert!(bytes.len() < sampled(u64::MAX).len());
+        assert!(bytes.len() > sampled(1).len());
+        assert_eq!(&bytes[..], &sampled(4)[..]);
+
+        assert_round_trip(&bytes, &value);
+    }
+
+    #[test]
+    fn interner_most_referenced() {
+        let mut interner = Interner::<String>::new();
+        for (i, user) in
+            [0, 1, 1, 2, 1, 2].iter().map(|&i| USERS[i]).enumerate()
+        {
+            let state =
+                Interning::<str, Panic>::start_interning(&mut interner, user);
+            if let SharingState::Started = state {
+                Interning::<str, Panic>::finish_interning(
+                    &mut interner,
+                    user,
+                    i,
+                )
+                .always_ok();
+            }
+        }
+
+        let top = interner.most_referenced(2);
+        assert_eq!(top.len(), 2);
+        assert_eq!((top[0].0.as_str(), top[0].1), (USERS[1], 3));
+        assert_eq!((top[1].0.as_str(), top[1].1), (USERS[2], 2));
+    }
+
+    #[test]
+    fn interner_rollback() {
+        fn start(interner: &mut Interner<String>, value: &str) -> SharingState {
+            Interning::<str, Panic>::start_interning(interner, value)
+        }
+
+        let mut interner = Interner::new();
+        start(&mut interner, "kept");
+        Interning::<str, Panic>::finish_interning(&mut interner, "kept", 0)
+            .always_ok();
+
+        let snapshot = interner.snapshot();
+        interner.push_scope();
+        start(&mut interner, "aborted");
+        interner.rollback(snapshot);
+
+        assert!(matches!(
+            start(&mut interner, "kept"),
+            SharingState::Finished(0)
+        ));
+        assert!(matches!(
+            start(&mut interner, "aborted"),
+            SharingState::Started
+        ));
+        assert!(!interner.pop_scope());
+    }
+
+    #[test]
+    fn interner_duplicate_policy() {
+        fn finish(
+            interner: &mut Interner<String>,
+            pos: usize,
+        ) -> Result<(), Error> {
+            Interning::<str, Error>::finish_interning(interner, "value", pos)
+        }
+
+        let mut interner = Interner::new();
+        Interning::<str, Error>::start_interning(&mut interner, "value");
+        finish(&mut interner, 0).unwrap();
+        let error = finish(&mut interner, 8).unwrap_err().to_string();
+        assert!(!error.contains("\"value\""));
+
+        interner.set_debug_values();
+        let error = finish(&mut interner, 8).unwrap_err().to_string();
+        assert!(error.contains("\"value\""));
+
+        interner.set_duplicate_policy(DuplicatePolicy::KeepFirst);
+        finish(&mut interner, 8).unwrap();
+        assert_eq!(interner.export(), [("value".to_string(), 0)]);
+
+        interner.set_duplicate_policy(DuplicatePolicy::Overwrite);
+        finish(&mut interner, 8).unwrap();
+        assert_eq!(interner.export(), [("value".to_string(), 8)]);
+    }
+
+    #[test]
+    fn observe_hits_and_misses() {
+        #[derive(Default)]
+        struct Events {
+            hits: Vec<usize>,
+            misses: Vec<usize>,
+        }
+
+        impl InternObserver<str> for Events {
+            fn hit(&mut self, _: &str, pos: usize) {
+                self.hits.push(pos);
+            }
+
+            fn miss(&mut self, _: &str, pos: usize) {
+                self.misses.push(pos);
+            }
+        }
+
+        let mut interning =
+            Observe::new(Interner::<String>::new(), Events::default());
+        for (i, user) in USERS.iter().chain(USERS.iter()).enumerate() {
+            let state =
+                Interning::<str, Panic>::start_interning(&mut interning, user);
+            if let SharingState::Started = state {
+                Interning::<str, Panic>::finish_interning(
+                    &mut interning,
+                    user,
+                    i,
+                )
+                .always_ok();
+            }
+        }
+
+        let (_, events) = interning.into_components();
+        assert_eq!(events.misses, [0, 1, 2, 3]);
+        assert_eq!(events.hits, [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn boxed_dyn_interning() {
+        let value = logs();
+
+        let strategies: [Box<dyn Interning<str, Panic>>; 2] = [
+            Box::new(Interner::<String>::new()),
+            Box::new(BTreeInterner::<String>::new()),
+        ];
+        for mut interning in strategies {
+            to_bytes_with::<_, Panic>(&value, &mut interning).always_ok();
+            assert_eq!(interning.len(), USERS.len());
+        }
+    }
+
+    #[test]
+    fn shared_intern_arcs() {
+        use ::alloc::sync::Arc;
+
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Log {
+            #[rkyv(with = SharedIntern)]
+            user: Arc<str>,
+            code: u16,
+        }
+
+        let users = USERS
+            .iter()
+            .map(|&user| Arc::<str>::from(user))
+            .collect::<Vec<_>>();
+        let mut value = Vec::new();
+        for i in 0..1000 {
+            value.push(Log {
+                user: users[i % users.len()].clone(),
+                code: (i % u16::MAX as usize) as u16,
+            });
+        }
+
+        let bytes = to_bytes::<Panic>(&value).always_ok();
+        assert!(bytes.len() < 20_000);
+
+        let deserialized = assert_round_trip(&bytes, &value);
+        for (i, log) in deserialized.iter().enumerate() {
+            let first = &deserialized[i % users.len()];
+            assert!(Arc::ptr_eq(&log.user, &first.user));
+        }
+    }
+
+    #[test]
+    fn shared_intern_rcs() {
+        use ::alloc::rc::Rc;
+
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Log {
+            #[rkyv(with = SharedIntern)]
+            user: Rc<str>,
+        }
+
+        let value = USERS
+            .iter()
+            .chain(USERS.iter())
+            .map(|&user| Log {
+                user: Rc::from(user),
+            })
+            .collect::<Vec<_>>();
+
+        let bytes = to_bytes::<Panic>(&value).always_ok();
+        let deserialized = assert_round_trip(&bytes, &value);
+        for (a, b) in deserialized.iter().zip(&deserialized[USERS.len()..]) {
+            assert!(Rc::ptr_eq(&a.user, &b.user));
+            assert_eq!(Rc::strong_count(&a.user), 2);
+        }
+    }
+
+    #[test]
+    fn shared_intern_arcs_and_rcs() {
+        use ::alloc::{rc::Rc, sync::Arc};
+
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Log {
+            #[rkyv(with = SharedIntern)]
+            user: Arc<str>,
+            #[rkyv(with = SharedIntern)]
+            owner: Rc<str>,
+        }
+
+        let value = USERS
+            .iter()
+            .chain(USERS.iter())
+            .map(|&user| Log {
+                user: Arc::from(user),
+                owner: Rc::from(user),
+            })
+            .collect::<Vec<_>>();
+
+        let bytes = to_bytes::<Panic>(&value).always_ok();
+        let deserialized = assert_round_trip(&bytes, &value);
+        for (a, b) in deserialized.iter().zip(&deserialized[USERS.len()..]) {
+            assert!(Arc::ptr_eq(&a.user, &b.user));
+            assert!(Rc::ptr_eq(&a.owner, &b.owner));
+            assert_eq!(Arc::strong_count(&a.user), 2);
+            assert_eq!(Rc::strong_count(&a.owner), 2);
+        }
+    }
+
+    #[test]
+    fn shared_intern_empty_values() {
+        use ::alloc::{rc::Rc, sync::Arc};
+
+        // The empty string takes up no bytes, so it shares its address with
+        // the value written after it.
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Log {
+            #[rkyv(with = SharedIntern)]
+            user: Arc<str>,
+            owner: Rc<str>,
+        }
+
+        let value = Log {
+            user: Arc::from(""),
+            owner: Rc::from("hello world"),
+        };
+
+        let bytes = to_bytes::<Panic>(&value).always_ok();
+        assert_round_trip(&bytes, &value);
+    }
+
+    #[test]
+    fn pooling_stats() {
+        use ::alloc::sync::Arc;
+
+        #[derive(Archive, Serialize, Deserialize)]
+        struct Log {
+            #[rkyv(with = SharedIntern)]
+            user: Arc<str>,
+        }
+
+        let value = (0..1000)
+            .map(|i| Log {
+                user: Arc::from(USERS[i % USERS.len()]),
+            })
+            .collect::<Vec<_>>();
+
+        let bytes = to_bytes::<Panic>(&value).always_ok();
+        let archived = unsafe {
+            access_unchecked::<Archived<Vec<Log>>>(&bytes)
+        };
+
+        let mut pool = StatsPooling::new(Pool::new());
+        deserialize_using::<Vec<Log>, _, Panic>(archived, &mut pool)
+            .always_ok();
+        let stats = pool.stats();
+        assert_eq!(stats.lookups, 1000);
+        assert_eq!(stats.distinct, USERS.len());
+        assert_eq!(stats.hits, 1000 - USERS.len());
+    }
+
+    #[cfg(feature = "internment")]
+    #[test]
+    fn shared_intern_internment() {
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Log {
+            #[rkyv(with = SharedIntern)]
+            user: internment::Intern<String>,
+        }
+
+        let value = USERS
+            .iter()
+            .chain(USERS.iter())
+            .map(|&user| Log {
+                user: internment::Intern::new(user.to_string()),
+            })
+            .collect::<Vec<_>>();
+
+        let bytes = to_bytes::<Panic>(&value).always_ok();
+        assert_round_trip(&bytes, &value);
+    }
+
+    #[cfg(feature = "ustr")]
+    #[test]
+    fn shared_intern_ustr() {
+        #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+        struct Log {
+            #[rkyv(with = SharedIntern)]
+            user: ustr::Ustr,
+        }
+
+        let value = USERS
+            .iter()
+            .chain(USERS.iter())
+            .map(|&user| Log {
+                user: ustr::Ustr::from(user),
+            })
+            .collect::<Vec<_>>();
+
+        let bytes = to_bytes::<Panic>(&value).always_ok();
+        assert_round_trip(&bytes, &value);
+    }
+
+    #[test]
+    fn interner_remove_pending() {
+        fn start(interner: &mut Interner<String>, value: &str) -> SharingState {
+            Interning::<str, Panic>::start_interning(interner, value)
+        }
+
+        let mut interner = Interner::new();
+        start(&mut interner, "finished");
+        Interning::<str, Panic>::finish_interning(&mut interner, "finished", 0)
+            .always_ok();
+        start(&mut interner, "failed");
+
+        assert_eq!(interner.pending().collect::<Vec<_>>(), ["failed"]);
+        assert!(matches!(
+            start(&mut interner, "failed"),
+            SharingState::Pending
+        ));
+
+        assert_eq!(interner.remove_pending(), 1);
+        assert_eq!(interner.pending().count(), 0);
+        assert!(matches!(
+            start(&mut interner, "finished"),
+            SharingState::Finished(0)
+        ));
+        assert!(matches!(
+            start(&mut interner, "failed"),
+            SharingState::Started
+        ));
+    }
+
+    // Compares against fixed archives, so this assumes the default 32-bit
+    // relative pointers and lengths.
+    #[test]
+    #[cfg(not(any(
+        feature = "pointer_width_16",
+        feature = "pointer_width_64",
+    )))]
+    fn interned_values_follow_endianness() {
+        #[derive(Archive, Serialize)]
+        struct Log {
+            #[rkyv(with = BorrowIntern<str>)]
+            user: String,
+            code: u16,
+        }
+
+        const LITTLE_ENDIAN_LOG: [u8; 16] = [
+            b'h', b'i', 0, 0, 0xfc, 0xff, 0xff, 0xff, 2, 0, 0, 0, 0x02, 0x01,
+            0, 0,
+        ];
+        const BIG_ENDIAN_LOG: [u8; 16] = [
+            b'h', b'i', 0, 0, 0xff, 0xff, 0xff, 0xfc, 0, 0, 0, 2, 0x01, 0x02,
+            0, 0,
+        ];
+
+        #[cfg(feature = "big_endian")]
+        let (native, foreign) = (BIG_ENDIAN_LOG, LITTLE_ENDIAN_LOG);
+        #[cfg(not(feature = "big_endian"))]
+        let (native, foreign) = (LITTLE_ENDIAN_LOG, BIG_ENDIAN_LOG);
+
+        let value = Log {
+            user: "hi".to_string(),
+            code: 0x0102,
+        };
+        let bytes = to_bytes::<Panic>(&value).always_ok();
+        assert_eq!(&bytes[..], &native[..]);
+
+        let mut fixture = AlignedVec::<16>::new();
+        fixture.extend_from_slice(&native);
+        let archived = unsafe { access_unchecked::<Archived<Log>>(&fixture) };
+        assert_eq!(archived.user.get(), "hi");
+        assert_eq!(archived.code.to_native(), 0x0102);
+
+        // Only the code can be read from the other byte order, since the
+        // offset to the user is out of bounds.
+        let mut fixture = AlignedVec::<16>::new();
+        fixture.extend_from_slice(&foreign);
+        let archived = unsafe { access_unchecked::<Archived<Log>>(&fixture) };
+        assert_eq!(archived.code.to_native(), 0x0201);
+    }
 }
@@ -0,0 +1,151 @@
+use alloc::{
+    borrow::{Cow, ToOwned},
+    string::String,
+};
+
+use rkyv::ser::sharing::SharingState;
+#[cfg(feature = "unicode-normalization")]
+use unicode_normalization::{is_nfc, UnicodeNormalization as _};
+
+use crate::Interning;
+
+/// An interning adapter which interns values under a normalized key.
+///
+/// Values which normalize to the same key share a single archived copy. The
+/// copy that gets archived is the first value encountered for each key, since
+/// interning only decides whether a value is written and never changes what is
+/// written.
+///
+/// # Example
+#[cfg_attr(feature = "hashbrown", doc = "```")]
+#[cfg_attr(not(feature = "hashbrown"), doc = "```ignore")]
+/// use rkyv_intern::{Interner, Normalize};
+///
+/// // "GET", "get", and "Get" all share one archived string.
+/// let interning = Normalize::lowercase(Interner::<String>::new());
+/// ```
+#[derive(Debug)]
+pub struct Normalize<I, F> {
+    interning: I,
+    normalize: F,
+}
+
+impl<I, F> Normalize<I, F> {
+    /// Returns a new adapter which interns values under the key returned by
+    /// `normalize`.
+    pub fn new(interning: I, normalize: F) -> Self {
+        Self {
+            interning,
+            normalize,
+        }
+    }
+
+    /// Consumes the adapter and returns the underlying interning.
+    pub fn into_inner(self) -> I {
+        self.interning
+    }
+}
+
+impl<I> Normalize<I, for<'a> fn(&'a str) -> Cow<'a, str>> {
+    /// Returns a new adapter which interns strings by their lowercase form.
+    ///
+    /// Every character with a lowercase mapping is lowercased, including
+    /// titlecase characters like `ǅ`. This is not full Unicode case folding,
+    /// so strings like `ß` and `SS` are still interned separately. The casing
+    /// that gets archived is that of the first string encountered for each
+    /// key; archiving the lowercase form instead is not supported.
+    pub fn lowercase(interning: I) -> Self {
+        Self::new(interning, lowercase)
+    }
+
+    /// Returns a new adapter which interns strings by their Unicode
+    /// Normalization Form C.
+    ///
+    /// Canonically equivalent strings share one archived copy even if they are
+    /// encoded differently.
+    #[cfg(feature = "unicode-normalization")]
+    pub fn nfc(interning: I) -> Self {
+        Self::new(interning, nfc)
+    }
+
+    /// Returns a new adapter which interns path-like strings by their
+    /// canonical form.
+    ///
+    /// Both `/` and `\` are treated as separators, and empty and `.`
+    /// components are dropped, so `a/b`, `a\b`, and `a/./b` share one archived
+    /// copy. `..` components are kept since resolving them can change which
+    /// file a path refers to.
+    pub fn path(interning: I) -> Self {
+        Self::new(interning, path)
+    }
+}
+
+fn lowercase(value: &str) -> Cow<'_, str> {
+    if value.chars().all(|c| c.to_lowercase().eq(Some(c))) {
+        Cow::Borrowed(value)
+    } else {
+        Cow::Owned(value.to_lowercase())
+    }
+}
+
+#[cfg(feature = "unicode-normalization")]
+fn nfc(value: &str) -> Cow<'_, str> {
+    if is_nfc(value) {
+        Cow::Borrowed(value)
+    } else {
+        Cow::Owned(value.nfc().collect())
+    }
+}
+
+fn path(value: &str) -> Cow<'_, str> {
+    let is_separator = |c| c == '/' || c == '\\';
+    let absolute = value.starts_with(is_separator);
+    let is_canonical = !value.contains('\\')
+        && value.split('/').enumerate().all(|(i, component)| {
+            (i == 0 && absolute) || (!component.is_empty() && component != ".")
+        });
+    if is_canonical {
+        return Cow::Borrowed(value);
+    }
+
+    let mut canonical = String::with_capacity(value.len());
+    if absolute {
+        canonical.push('/');
+    }
+    let components = value
+        .split(is_separator)
+        .filter(|component| !component.is_empty() && *component != ".");
+    for (i, component) in components.enumerate() {
+        if i != 0 {
+            canonical.push('/');
+        }
+        canonical.push_str(component);
+    }
+    Cow::Owned(canonical)
+}
+
+impl<I, F, T, E> Interning<T, E> for Normalize<I, F>
+where
+    I: Interning<T, E>,
+    F: for<'a> Fn(&'a T) -> Cow<'a, T>,
+    T: ToOwned + ?Sized,
+{
+    fn start_interning(&mut self, value: &T) -> SharingState {
+        let key = (self.normalize)(value);
+        self.interning.start_interning(&key)
+    }
+
+    fn finish_interning(&mut self, value: &T, pos: usize) -> Result<(), E> {
+        let key = (self.normalize)(value);
+        self.interning.finish_interning(&key, pos)
+    }
+
+    fn contains(&self, value: &T) -> bool {
+        let key = (self.normalize)(value);
+        self.interning.contains(&key)
+    }
+
+    fn len(&self) -> usize {
+        self.interning.len()
+    }
+}
@@ -0,0 +1,96 @@
+use rkyv::ser::sharing::SharingState;
+
+use crate::Interning;
+
+/// A callback for interning events.
+///
+/// Every method has an empty default implementation, so observers only need
+/// to implement the events they care about.
+pub trait InternObserver<T: ?Sized> {
+    /// Called when a value resolves to an already-interned value at `pos`.
+    fn hit(&mut self, value: &T, pos: usize) {
+        let _ = (value, pos);
+    }
+
+    /// Called when a new value is interned at `pos`.
+    fn miss(&mut self, value: &T, pos: usize) {
+        let _ = (value, pos);
+    }
+}
+
+/// An interning adapter which reports interning events to an
+/// [`InternObserver`].
+///
+/// # Example
+#[cfg_attr(feature = "hashbrown", doc = "```")]
+#[cfg_attr(not(feature = "hashbrown"), doc = "```ignore")]
+/// use rkyv_intern::{InternObserver, Interner, Observe};
+///
+/// #[derive(Default)]
+/// struct CountMisses(usize);
+///
+/// impl InternObserver<str> for CountMisses {
+///     fn miss(&mut self, _: &str, _: usize) {
+///         self.0 += 1;
+///     }
+/// }
+///
+/// let interning =
+///     Observe::new(Interner::<String>::new(), CountMisses::default());
+/// ```
+#[derive(Debug, Default)]
+pub struct Observe<I, O> {
+    interning: I,
+    observer: O,
+}
+
+impl<I, O> Observe<I, O> {
+    /// Returns a new adapter which reports the events of `interning` to
+    /// `observer`.
+    pub fn new(interning: I, observer: O) -> Self {
+        Self {
+            interning,
+            observer,
+        }
+    }
+
+    /// Returns the observer.
+    pub fn observer(&self) -> &O {
+        &self.observer
+    }
+
+    /// Consumes the adapter and returns the underlying interning and the
+    /// observer.
+    pub fn into_components(self) -> (I, O) {
+        (self.interning, self.observer)
+    }
+}
+
+impl<I, O, T, E> Interning<T, E> for Observe<I, O>
+where
+    I: Interning<T, E>,
+    O: InternObserver<T>,
+    T: ?Sized,
+{
+    fn start_interning(&mut self, value: &T) -> SharingState {
+        let state = self.interning.start_interning(value);
+        if let SharingState::Finished(pos) = state {
+            self.observer.hit(value, pos);
+        }
+        state
+    }
+
+    fn finish_interning(&mut self, value: &T, pos: usize) -> Result<(), E> {
+        self.interning.finish_interning(value, pos)?;
+        self.observer.miss(value, pos);
+        Ok(())
+    }
+
+    fn contains(&self, value: &T) -> bool {
+        self.interning.contains(value)
+    }
+
+    fn len(&self) -> usize {
+        self.interning.len()
+    }
+}
@@ -0,0 +1,115 @@
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, ffi::CString, string::String, vec::Vec};
+use core::{ffi::CStr, mem::size_of};
+
+/// A value whose size can be measured by interning policies.
+///
+/// The size of a value is the number of bytes of data it holds, including
+/// data behind owned pointers. For strings and byte slices, this is their
+/// length in bytes. Implement this for your own types to use them with
+/// [`MinSize`](crate::MinSize), [`MaxSize`](crate::MaxSize), and the other
+/// size-based policies.
+pub trait ByteSize {
+    /// Returns the size of the value in bytes.
+    fn byte_size(&self) -> usize;
+}
+
+macro_rules! impl_primitive {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl ByteSize for $ty {
+                fn byte_size(&self) -> usize {
+                    size_of::<Self>()
+                }
+            }
+        )*
+    };
+}
+
+impl_primitive! {
+    (), bool, char,
+    u8, u16, u32, u64, u128, usize,
+    i8, i16, i32, i64, i128, isize,
+    f32, f64,
+}
+
+macro_rules! impl_tuple {
+    ($($ty:ident),*) => {
+        impl<$($ty: ByteSize),*> ByteSize for ($($ty,)*) {
+            #[allow(non_snake_case)]
+            fn byte_size(&self) -> usize {
+                let ($($ty,)*) = self;
+                0 $(+ $ty.byte_size())*
+            }
+        }
+    };
+}
+
+impl_tuple!(A);
+impl_tuple!(A, B);
+impl_tuple!(A, B, C);
+impl_tuple!(A, B, C, D);
+
+impl ByteSize for str {
+    fn byte_size(&self) -> usize {
+        self.len()
+    }
+}
+
+impl ByteSize for CStr {
+    fn byte_size(&self) -> usize {
+        self.to_bytes_with_nul().len()
+    }
+}
+
+impl<T: ByteSize> ByteSize for [T] {
+    fn byte_size(&self) -> usize {
+        self.iter().map(ByteSize::byte_size).sum()
+    }
+}
+
+impl<T: ByteSize, const N: usize> ByteSize for [T; N] {
+    fn byte_size(&self) -> usize {
+        self.as_slice().byte_size()
+    }
+}
+
+impl<T: ByteSize> ByteSize for Option<T> {
+    fn byte_size(&self) -> usize {
+        self.as_ref().map_or(0, ByteSize::byte_size)
+    }
+}
+
+impl<T: ByteSize + ?Sized> ByteSize for &T {
+    fn byte_size(&self) -> usize {
+        (**self).byte_size()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl ByteSize for String {
+    fn byte_size(&self) -> usize {
+        self.len()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl ByteSize for CString {
+    fn byte_size(&self) -> usize {
+        self.as_c_str().byte_size()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: ByteSize> ByteSize for Vec<T> {
+    fn byte_size(&self) -> usize {
+        self.as_slice().byte_size()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: ByteSize + ?Sized> ByteSize for Box<T> {
+    fn byte_size(&self) -> usize {
+        (**self).byte_size()
+    }
+}
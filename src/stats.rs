@@ -0,0 +1,174 @@
+use rkyv::{
+    de::{ErasedPtr, Pooling, PoolingState},
+    ser::sharing::SharingState,
+};
+
+use crate::{ByteSize, Interning};
+
+/// Statistics collected by [`StatsInterning`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct InterningStats {
+    /// The number of values looked up.
+    pub lookups: usize,
+    /// The number of lookups which resolved to an already-interned value.
+    pub hits: usize,
+    /// The number of distinct values interned.
+    pub distinct: usize,
+    /// An estimate of the number of bytes saved by interning.
+    ///
+    /// Each hit is counted as saving the [`ByteSize`] of the value, which is
+    /// the byte length for strings and byte slices.
+    pub bytes_saved: usize,
+}
+
+impl InterningStats {
+    /// Returns the number of lookups which did not resolve to an
+    /// already-interned value.
+    pub fn misses(&self) -> usize {
+        self.lookups - self.hits
+    }
+}
+
+/// An interning adapter which collects statistics about another interning.
+///
+/// # Example
+#[cfg_attr(feature = "hashbrown", doc = "```")]
+#[cfg_attr(not(feature = "hashbrown"), doc = "```ignore")]
+/// use rkyv_intern::{Interner, StatsInterning};
+///
+/// let interning = StatsInterning::new(Interner::<String>::new());
+/// assert_eq!(interning.stats().lookups, 0);
+/// ```
+#[derive(Debug, Default)]
+pub struct StatsInterning<I> {
+    interning: I,
+    stats: InterningStats,
+}
+
+impl<I> StatsInterning<I> {
+    /// Returns a new adapter which collects statistics about the given
+    /// interning.
+    pub fn new(interning: I) -> Self {
+        Self {
+            interning,
+            stats: InterningStats::default(),
+        }
+    }
+
+    /// Returns the statistics collected so far.
+    pub fn stats(&self) -> InterningStats {
+        self.stats
+    }
+
+    /// Consumes the adapter and returns the underlying interning.
+    pub fn into_inner(self) -> I {
+        self.interning
+    }
+}
+
+impl<I, T, E> Interning<T, E> for StatsInterning<I>
+where
+    I: Interning<T, E>,
+    T: ByteSize + ?Sized,
+{
+    fn start_interning(&mut self, value: &T) -> SharingState {
+        self.stats.lookups += 1;
+        let state = self.interning.start_interning(value);
+        if let SharingState::Finished(_) = state {
+            self.stats.hits += 1;
+            self.stats.bytes_saved += value.byte_size();
+        }
+        state
+    }
+
+    fn finish_interning(&mut self, value: &T, pos: usize) -> Result<(), E> {
+        self.interning.finish_interning(value, pos)?;
+        self.stats.distinct += 1;
+        Ok(())
+    }
+
+    fn contains(&self, value: &T) -> bool {
+        self.interning.contains(value)
+    }
+
+    fn len(&self) -> usize {
+        self.interning.len()
+    }
+}
+
+/// Statistics collected by [`StatsPooling`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolingStats {
+    /// The number of shared values looked up.
+    pub lookups: usize,
+    /// The number of lookups which reused an already-deserialized value.
+    ///
+    /// Each hit is an allocation avoided.
+    pub hits: usize,
+    /// The number of distinct shared values deserialized.
+    pub distinct: usize,
+}
+
+/// A deserializer adapter which collects statistics about pooled values.
+///
+/// Wrapping the pool used to deserialize [`SharedIntern`](crate::SharedIntern)
+/// fields shows how often shared deserialization reuses a value instead of
+/// allocating a new one.
+///
+/// # Example
+#[cfg_attr(feature = "alloc", doc = "```")]
+#[cfg_attr(not(feature = "alloc"), doc = "```ignore")]
+/// use rkyv::de::Pool;
+/// use rkyv_intern::StatsPooling;
+///
+/// let pool = StatsPooling::new(Pool::new());
+/// assert_eq!(pool.stats().lookups, 0);
+/// ```
+#[derive(Debug, Default)]
+pub struct StatsPooling<P> {
+    pooling: P,
+    stats: PoolingStats,
+}
+
+impl<P> StatsPooling<P> {
+    /// Returns a new adapter which collects statistics about the given
+    /// pooling.
+    pub fn new(pooling: P) -> Self {
+        Self {
+            pooling,
+            stats: PoolingStats::default(),
+        }
+    }
+
+    /// Returns the statistics collected so far.
+    pub fn stats(&self) -> PoolingStats {
+        self.stats
+    }
+
+    /// Consumes the adapter and returns the underlying pooling.
+    pub fn into_inner(self) -> P {
+        self.pooling
+    }
+}
+
+impl<P: Pooling<E>, E> Pooling<E> for StatsPooling<P> {
+    fn start_pooling(&mut self, address: usize) -> PoolingState {
+        self.stats.lookups += 1;
+        let state = self.pooling.start_pooling(address);
+        if let PoolingState::Finished(_) = state {
+            self.stats.hits += 1;
+        }
+        state
+    }
+
+    unsafe fn finish_pooling(
+        &mut self,
+        address: usize,
+        ptr: ErasedPtr,
+        drop: unsafe fn(ErasedPtr),
+    ) -> Result<(), E> {
+        unsafe { self.pooling.finish_pooling(address, ptr, drop)? };
+        self.stats.distinct += 1;
+        Ok(())
+    }
+}
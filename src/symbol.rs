@@ -0,0 +1,142 @@
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+use rkyv::{
+    rancor::{fail, Source},
+    ser::sharing::SharingState,
+};
+
+use crate::{AlreadyFinished, Interning, NotStarted};
+
+/// An interner which reuses an existing symbol table.
+///
+/// Instead of keeping its own copy of each value, this interner asks a
+/// function for the symbol of each value and records positions by symbol. This
+/// lets applications which already intern values at runtime (for example with
+/// `lasso` or `string-interner`) serialize without a second copy of every
+/// value.
+///
+/// Symbols are used as indices, so they should be small and dense. Values with
+/// symbols at or above the maximum set with
+/// [`set_max_symbols`](Self::set_max_symbols) are not interned. The symbol
+/// function is also called by [`contains`](Interning::contains), so a function
+/// which interns values it hasn't seen may add them to the symbol table.
+///
+/// # Example
+///
+/// ```
+/// use rkyv_intern::SymbolInterner;
+///
+/// let mut symbols = Vec::<String>::new();
+/// let interning = SymbolInterner::new(|value: &str| {
+///     match symbols.iter().position(|s| s == value) {
+///         Some(symbol) => symbol,
+///         None => {
+///             symbols.push(value.to_string());
+///             symbols.len() - 1
+///         }
+///     }
+/// });
+/// ```
+pub struct SymbolInterner<F> {
+    symbol_of: RefCell<F>,
+    symbol_to_pos: Vec<Option<Option<usize>>>,
+    max_symbols: usize,
+    len: usize,
+}
+
+/// The default maximum number of symbols for a [`SymbolInterner`].
+pub const DEFAULT_MAX_SYMBOLS: usize = 1 << 20;
+
+impl<F> SymbolInterner<F> {
+    /// Returns a new, empty interner which uses `symbol_of` to get the symbol
+    /// of each value.
+    pub fn new(symbol_of: F) -> Self {
+        Self {
+            symbol_of: RefCell::new(symbol_of),
+            symbol_to_pos: Vec::new(),
+            max_symbols: DEFAULT_MAX_SYMBOLS,
+            len: 0,
+        }
+    }
+
+    /// Sets the number of symbols which can be interned.
+    ///
+    /// The interner keeps a table entry for every symbol below the largest
+    /// one it has seen, so this bounds its memory usage. Values with symbols
+    /// at or above `max_symbols` are serialized every time they are
+    /// encountered. By default, this is [`DEFAULT_MAX_SYMBOLS`].
+    pub fn set_max_symbols(&mut self, max_symbols: usize) {
+        self.max_symbols = max_symbols;
+    }
+
+    /// Returns the number of values in the interner.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the interner is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Consumes the interner and returns the symbol function.
+    pub fn into_inner(self) -> F {
+        self.symbol_of.into_inner()
+    }
+}
+
+impl<F, T, E> Interning<T, E> for SymbolInterner<F>
+where
+    F: FnMut(&T) -> usize,
+    T: ?Sized,
+    E: Source,
+{
+    fn start_interning(&mut self, value: &T) -> SharingState {
+        let symbol = (self.symbol_of.get_mut())(value);
+        if symbol >= self.symbol_to_pos.len() {
+            match symbol.checked_add(1) {
+                Some(len) if len <= self.max_symbols => {
+                    self.symbol_to_pos.resize(len, None);
+                }
+                _ => return SharingState::Started,
+            }
+        }
+
+        match self.symbol_to_pos[symbol] {
+            None => {
+                self.symbol_to_pos[symbol] = Some(None);
+                self.len += 1;
+                SharingState::Started
+            }
+            Some(None) => SharingState::Pending,
+            Some(Some(pos)) => SharingState::Finished(pos),
+        }
+    }
+
+    fn finish_interning(&mut self, value: &T, pos: usize) -> Result<(), E> {
+        let symbol = (self.symbol_of.get_mut())(value);
+        match self.symbol_to_pos.get_mut(symbol) {
+            None if symbol >= self.max_symbols => Ok(()),
+            None | Some(None) => fail!(NotStarted),
+            Some(Some(Some(existing))) => fail!(AlreadyFinished {
+                value: None,
+                existing: *existing,
+                new: pos,
+            }),
+            Some(Some(x)) => {
+                *x = Some(pos);
+                Ok(())
+            }
+        }
+    }
+
+    fn contains(&self, value: &T) -> bool {
+        let symbol = (self.symbol_of.borrow_mut())(value);
+        self.symbol_to_pos.get(symbol).is_some_and(Option::is_some)
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}